@@ -34,4 +34,4 @@ RB
   shift || true
 done
 
-cargo run --release
+CP_AGGREGATE=1 cargo run --release
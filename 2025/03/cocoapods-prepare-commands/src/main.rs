@@ -1,16 +1,20 @@
 use core::time;
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     collections::{BTreeMap, HashMap, HashSet},
     fs::{self, File},
+    io::{BufWriter, Write},
     ops::Range,
     path::{self, Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::bail;
 use assoc::AssocExt;
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use git2::{Commit, Delta, ObjectType, Oid, Repository, Tree, TreeEntry, TreeWalkResult};
+use globset::{Glob, GlobMatcher};
 use rayon::{iter::IterBridge, prelude::*};
 use serde::{Deserialize, Serialize};
 
@@ -18,17 +22,56 @@ use serde::{Deserialize, Serialize};
 struct Podspec<'a> {
     #[serde(borrow)]
     name: Cow<'a, str>,
+    /// Only missing for subspecs, which share their parent's version.
+    #[serde(default)]
     version: Cow<'a, str>,
     prepare_command: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    dependencies: Option<BTreeMap<Cow<'a, str>, Vec<Cow<'a, str>>>>,
+    #[serde(default)]
+    source: Option<Box<PodspecSource>>,
+    /// Flattened into their own results by `parse_podspec`.
+    #[serde(borrow, default, skip_serializing)]
+    subspecs: Option<Vec<Podspec<'a>>>,
 
-    #[serde(skip)]
-    published: DateTime<Utc>,
+    #[serde(skip_deserializing)]
+    prepare_command_kind: Option<PrepareCommandKind>,
+
+    #[serde(skip_deserializing)]
+    published: Option<DateTime<Utc>>,
 
     #[serde(skip_deserializing)]
     loaded_from: Option<String>,
 
     #[serde(skip_deserializing)]
-    commits: Vec<(String, String, String)>,
+    commits: Vec<(String, String, String, Option<PrepareCommandDelta>)>,
+
+    /// Set by `collapse_versions` to the `first..last` run of versions this
+    /// podspec's prepare command stands for.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    versions: Option<String>,
+}
+
+/// Where a pod's code is fetched from. Sources other than git and http
+/// (svn, hg, path) and malformed ones are kept as-is.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum PodspecSource {
+    Git {
+        git: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+    },
+    Http {
+        http: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sha256: Option<String>,
+    },
+    Other(serde_json::Value),
 }
 
 #[derive(Debug, Serialize)]
@@ -36,7 +79,6 @@ struct Podspec<'a> {
 enum Res {
     Podspec(Podspec<'static>),
     Error { error: String, path: String },
-    NoPrepareCommand,
 }
 
 impl Podspec<'_> {
@@ -45,108 +87,572 @@ impl Podspec<'_> {
             name: self.name.into_owned().into(),
             version: self.version.into_owned().into(),
             prepare_command: self.prepare_command.map(|s| s.into_owned().into()),
+            dependencies: self.dependencies.map(|dependencies| {
+                dependencies
+                    .into_iter()
+                    .map(|(name, requirements)| {
+                        (
+                            name.into_owned().into(),
+                            requirements
+                                .into_iter()
+                                .map(|r| r.into_owned().into())
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            }),
+            source: self.source,
+            subspecs: self
+                .subspecs
+                .map(|subspecs| subspecs.into_iter().map(Podspec::into_owned).collect()),
+            prepare_command_kind: self.prepare_command_kind,
             published: self.published,
             loaded_from: self.loaded_from,
             commits: self.commits,
+            versions: self.versions,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+enum PrepareCommandKind {
+    Download,
+    Unzip,
+    FileWrite,
+    RubyScript,
+    ShellScript,
+    Other,
+}
+
+/// Buckets a prepare command by the most notable thing it does, checking the
+/// riskiest kinds first since commands often do several things.
+fn classify(cmd: &str) -> PrepareCommandKind {
+    let words: Vec<&str> = cmd
+        .split(|c: char| c.is_whitespace() || ";|&()`$\"'".contains(c))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let has_word = |names: &[&str]| words.iter().any(|w| names.contains(w));
+
+    if has_word(&["curl", "wget"]) {
+        PrepareCommandKind::Download
+    } else if has_word(&["unzip", "tar", "gunzip", "7z", "ditto"]) {
+        PrepareCommandKind::Unzip
+    } else if cmd.contains("<<") || (has_word(&["echo", "printf", "cat"]) && cmd.contains('>')) {
+        PrepareCommandKind::FileWrite
+    } else if has_word(&["ruby", "rake", "bundle"]) || words.iter().any(|w| w.ends_with(".rb")) {
+        PrepareCommandKind::RubyScript
+    } else if has_word(&["sh", "bash", "zsh", "source", "."])
+        || words
+            .iter()
+            .any(|w| w.ends_with(".sh") || w.starts_with("./"))
+    {
+        PrepareCommandKind::ShellScript
+    } else {
+        PrepareCommandKind::Other
+    }
+}
+
+/// Bumped whenever the shape of `IterResult` changes.
+const SCHEMA_VERSION: u32 = 2;
+
 #[derive(Debug, Serialize)]
 struct IterResult {
+    schema_version: u32,
+    generated_at: DateTime<Utc>,
+    repo: String,
     commit: String,
     podspecs: BTreeMap<String, Vec<Res>>,
 }
 
-fn iter_repo(repo: &str) -> anyhow::Result<IterResult> {
-    let repository = Repository::open(repo)?;
+impl IterResult {
+    fn new(repo: &str, commit: String, podspecs: BTreeMap<String, Vec<Res>>) -> Self {
+        IterResult {
+            schema_version: SCHEMA_VERSION,
+            generated_at: Utc::now(),
+            repo: repo.to_string(),
+            commit,
+            podspecs,
+        }
+    }
+}
+
+/// Each path's history, newest first, noting the commits that changed the
+/// prepare command of the podspec at that path.
+type CommitsByPath = HashMap<
+    String,
+    Vec<(
+        Delta,
+        DateTime<FixedOffset>,
+        Oid,
+        Option<PrepareCommandDelta>,
+    )>,
+>;
 
+fn fetch_master(repository: &Repository) -> anyhow::Result<Commit<'_>> {
     let mut remote = repository.find_remote("origin")?;
     println!("Fetching...");
     remote.fetch(&["master"], None, None)?;
     let branch = repository.find_branch("origin/master", git2::BranchType::Remote)?;
     let commit = branch.get().peel_to_commit()?;
     println!("Commit: {}", commit.id());
+    Ok(commit)
+}
 
-    let commits_by_path = {
-        println!("Finding dates...");
-        let mut commits: HashMap<String, Vec<_>> = HashMap::new();
-        let mut c = commit.clone();
-        loop {
-            let id = c.id();
-            let date = c.time();
-            let date = FixedOffset::east_opt(date.offset_minutes() * 60)
-                .unwrap()
-                .timestamp_opt(date.seconds(), 0)
-                .unwrap();
-            // let out = format!("{}: {:?} ({:?})", c.id(), c.time(), c.summary());
-            let Ok((p, d)) = thing(&repository, &c) else {
-                println!("Error: {}", c.id());
-                break;
-            };
-            for (delta, path) in d {
-                commits.entry(path).or_default().push((delta, date, c.id()));
+/// Walks the first-parent history of `commit`. With a `cache`, the prepare
+/// commands of podspecs matching `filter` are read on both sides of each
+/// change, through it, since the blob after one commit is the blob before
+/// the next. Without one, only the deltas are collected.
+fn commits_by_path(
+    repository: &Repository,
+    commit: &Commit<'_>,
+    filter: Option<&GlobMatcher>,
+    mut cache: Option<&mut PodspecCache>,
+) -> CommitsByPath {
+    println!("Finding dates...");
+    let mut commits: CommitsByPath = HashMap::new();
+    let mut c = commit.clone();
+    loop {
+        let date = c.time();
+        let date = FixedOffset::east_opt(date.offset_minutes() * 60)
+            .unwrap()
+            .timestamp_opt(date.seconds(), 0)
+            .unwrap();
+        // let out = format!("{}: {:?} ({:?})", c.id(), c.time(), c.summary());
+        let Ok((p, d)) = thing(repository, &c) else {
+            println!("Error: {}", c.id());
+            break;
+        };
+        let tracked = |path: &str| path.ends_with(".podspec.json") && matches_filter(filter, path);
+        // Each commit's trees are only looked up once, and only if needed.
+        let trees = if cache.is_some() && d.iter().any(|(_, path)| tracked(path)) {
+            match (c.tree(), p.as_ref().map(Commit::tree).transpose()) {
+                (Ok(tree), Ok(parent_tree)) => Some((parent_tree, tree)),
+                (Err(e), _) | (_, Err(e)) => {
+                    println!("Error: {}: {e}", c.id());
+                    None
+                }
             }
-            c = p;
+        } else {
+            None
+        };
+        for (delta, path) in d {
+            let change = match (cache.as_deref_mut(), &trees) {
+                (Some(cache), Some((parent_tree, tree))) if tracked(&path) => {
+                    prepare_command_change(repository, cache, parent_tree.as_ref(), tree, &path)
+                        .unwrap_or_else(|e| {
+                            println!("Error: {} {path}: {e}", c.id());
+                            None
+                        })
+                }
+                _ => None,
+            };
+            commits
+                .entry(path)
+                .or_default()
+                .push((delta, date, c.id(), change));
         }
-        commits
+        let Some(p) = p else {
+            break;
+        };
+        c = p;
+    }
+    commits
+}
+
+/// How the prepare command of the podspec at `path` changed from
+/// `parent_tree` to `tree`.
+fn prepare_command_change(
+    repository: &Repository,
+    cache: &mut PodspecCache,
+    parent_tree: Option<&Tree<'_>>,
+    tree: &Tree<'_>,
+    path: &str,
+) -> anyhow::Result<Option<PrepareCommandDelta>> {
+    let path = Path::new(path.trim_start_matches("./"));
+    let before = match parent_tree {
+        Some(parent_tree) => prepare_command_at(repository, cache, parent_tree, path)?,
+        None => None,
     };
+    let after = prepare_command_at(repository, cache, tree, path)?;
+    Ok(PrepareCommandDelta::between(&before, &after))
+}
+
+fn is_podspec(entry: &TreeEntry) -> bool {
+    entry.kind() == Some(git2::ObjectType::Blob) && entry.name_bytes().ends_with(b".podspec.json")
+}
+
+/// The pod a podspec belongs to, going by its file name, so it can be
+/// filtered before the JSON is parsed.
+fn pod_name(path: &str) -> &str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    file_name.trim_end_matches(".podspec.json")
+}
+
+/// Whether the podspec at `path` should be walked, given the `--filter` glob.
+fn matches_filter(filter: Option<&GlobMatcher>, path: &str) -> bool {
+    filter.is_none_or(|filter| filter.is_match(pod_name(path)))
+}
+
+/// Describes why `content` didn't parse as a podspec, calling out invalid
+/// UTF-8 separately so the survey can tell bad encodings from bad JSON.
+fn podspec_error(content: &[u8], error: serde_json::Error) -> String {
+    match std::str::from_utf8(content) {
+        Err(e) => format!("invalid UTF-8 at byte {}", e.valid_up_to()),
+        Ok(_) => error.to_string(),
+    }
+}
+
+/// Parses the podspec at `path`, returning the pod name it should be grouped
+/// under along with the podspec and each subspec that has a prepare command.
+fn parse_podspec(
+    path: &str,
+    content: &[u8],
+    commits_by_path: &CommitsByPath,
+) -> Vec<(String, Res)> {
+    let podspec: Podspec<'_> = match serde_json::from_slice(content) {
+        Ok(podspec) => podspec,
+        Err(e) => {
+            return vec![(
+                pod_name(path).to_string(),
+                Res::Error {
+                    error: podspec_error(content, e),
+                    path: path.to_string(),
+                },
+            )];
+        }
+    };
+    let pod = podspec.name.to_string();
+    let mut podspecs = vec![];
+    flatten_subspecs(podspec, &mut podspecs);
+    if podspecs.is_empty() {
+        return vec![];
+    }
+
+    let history = commits_by_path
+        .get(&format!("./{}", path))
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    // History is newest-first, so the last addition is when it was published.
+    let published = history
+        .iter()
+        .rfind(|(d, _, _, _)| *d == Delta::Added)
+        .map(|(_, t, _, _)| t.with_timezone(&Utc));
+
+    let commits: Vec<_> = history
+        .iter()
+        .map(|(d, t, c, change)| (format!("{:?}", d), t.to_rfc3339(), c.to_string(), *change))
+        .collect();
+
+    podspecs
+        .into_iter()
+        .map(|mut podspec| {
+            podspec.loaded_from = Some(path.to_string());
+            podspec.published = published;
+            podspec.commits = commits.clone();
+            (pod.clone(), Res::Podspec(podspec.into_owned()))
+        })
+        .collect()
+}
+
+/// Collects `podspec` and its nested subspecs that have a prepare command,
+/// classified and named by their full `Parent/Child` path, as CocoaPods
+/// names subspecs.
+fn flatten_subspecs<'a>(mut podspec: Podspec<'a>, podspecs: &mut Vec<Podspec<'a>>) {
+    let subspecs = podspec.subspecs.take().unwrap_or_default();
+    let (name, version) = (podspec.name.clone(), podspec.version.clone());
+    if let Some(prepare_command) = &podspec.prepare_command {
+        podspec.prepare_command_kind = Some(classify(prepare_command));
+        podspecs.push(podspec);
+    }
+    for mut subspec in subspecs {
+        subspec.name = format!("{name}/{}", subspec.name).into();
+        if subspec.version.is_empty() {
+            subspec.version = version.clone();
+        }
+        flatten_subspecs(subspec, podspecs);
+    }
+}
+
+/// What to do with a podspec that fails to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WalkMode {
+    /// Record it as a `Res::Error` and keep going, for surveys.
+    CollectErrors,
+    /// Stop the walk with an error naming its path, for CI.
+    FailFast,
+}
+
+impl WalkMode {
+    fn check(self, res: &Res) -> anyhow::Result<()> {
+        if let (WalkMode::FailFast, Res::Error { error, path }) = (self, res) {
+            bail!("Failed to parse {path}: {error}");
+        }
+        Ok(())
+    }
+}
+
+/// Loads the blob of the podspec `entry`, failing if its name isn't UTF-8 or
+/// its object can't be read.
+fn read_podspec<'r>(
+    repository: &'r Repository,
+    entry: &TreeEntry<'_>,
+) -> anyhow::Result<git2::Blob<'r>> {
+    if entry.name().is_none() {
+        bail!("file name isn't valid UTF-8");
+    }
+    Ok(entry.to_object(repository)?.peel_to_blob()?)
+}
+
+/// Walks the podspecs at the tip of master, calling `f` with each one that
+/// has a prepare command as soon as it's parsed. Podspecs of pods that don't
+/// match `filter` are skipped unread. Prepare command changes are only
+/// traced through history when given a `cache`. Returns the commit walked.
+fn walk_repo(
+    repo: &str,
+    mode: WalkMode,
+    filter: Option<&GlobMatcher>,
+    cache: Option<&mut PodspecCache>,
+    mut f: impl FnMut(String, Res) -> anyhow::Result<()>,
+) -> anyhow::Result<String> {
+    let repository = Repository::open(repo)?;
+    let commit = fetch_master(&repository)?;
+    let commits_by_path = commits_by_path(&repository, &commit, filter, cache);
 
     let tree = commit.tree()?;
-    let mut podspecs: BTreeMap<String, Vec<Res>> = BTreeMap::new();
-    tree.walk(git2::TreeWalkMode::PostOrder, |s, entry| {
-        if entry.kind() != Some(git2::ObjectType::Blob) {
+    let mut error = None;
+    let walked = tree.walk(git2::TreeWalkMode::PostOrder, |s, entry| {
+        if !is_podspec(entry) {
             return TreeWalkResult::Ok;
         }
-        if !entry.name_bytes().ends_with(b".podspec.json") {
+        let path = format!("{}{}", s, String::from_utf8_lossy(entry.name_bytes()));
+        if !matches_filter(filter, &path) {
             return TreeWalkResult::Ok;
         }
-        let binding = entry.to_object(&repository).unwrap();
-        let blob = binding.as_blob().unwrap();
-        let mut podspec: Podspec<'_> = match serde_json::from_slice(blob.content()) {
-            Ok(podspec) => podspec,
-            Err(e) => {
-                podspecs
-                    .entry(
-                        entry
-                            .name()
-                            .unwrap()
-                            .trim_end_matches(".podspec.json")
-                            .to_string(),
-                    )
-                    .or_default()
-                    .push(Res::Error {
-                        error: e.to_string(),
-                        path: format!("{}{}", s, entry.name().unwrap()),
-                    });
-                return TreeWalkResult::Ok;
-            }
+        let parsed = match read_podspec(&repository, entry) {
+            Ok(blob) => parse_podspec(&path, blob.content(), &commits_by_path),
+            Err(e) => vec![(
+                pod_name(&path).to_string(),
+                Res::Error {
+                    error: e.to_string(),
+                    path: path.clone(),
+                },
+            )],
         };
-        if podspec.prepare_command.is_none() {
-            return TreeWalkResult::Ok;
+        for (name, res) in parsed {
+            if let Err(e) = mode.check(&res).and_then(|()| f(name, res)) {
+                error = Some(e);
+                return TreeWalkResult::Abort;
+            }
         }
 
-        podspec.loaded_from = Some(format!("{}{}", s, entry.name().unwrap()));
+        TreeWalkResult::Ok
+    });
+    // Aborting the walk surfaces as a git error, so report the cause instead.
+    if let Some(e) = error {
+        return Err(e);
+    }
+    walked?;
+    Ok(commit.id().to_string())
+}
 
-        podspec.commits = commits_by_path
-            .get(&format!("./{}", podspec.loaded_from.as_ref().unwrap()))
-            .map(|v| {
-                v.iter()
-                    .map(|(d, t, c)| (format!("{:?}", d), t.to_rfc3339(), c.to_string()))
-                    .collect()
-            })
-            .unwrap_or_default();
-        podspecs
-            .entry(podspec.name.to_string())
-            .or_default()
-            .push(Res::Podspec(podspec.into_owned()));
+fn iter_repo(
+    repo: &str,
+    mode: WalkMode,
+    filter: Option<&GlobMatcher>,
+    cache: Option<&mut PodspecCache>,
+) -> anyhow::Result<IterResult> {
+    let mut podspecs: BTreeMap<String, Vec<Res>> = BTreeMap::new();
+    let commit = walk_repo(repo, mode, filter, cache, |name, res| {
+        podspecs.entry(name).or_default().push(res);
+        Ok(())
+    })?;
+    Ok(IterResult::new(repo, commit, podspecs))
+}
 
-        TreeWalkResult::Ok
+/// Writes each podspec with a prepare command as its own JSON line while the
+/// tree is walked, instead of collecting them into an `IterResult`.
+fn stream_repo(
+    repo: &str,
+    mode: WalkMode,
+    filter: Option<&GlobMatcher>,
+    cache: Option<&mut PodspecCache>,
+    output: impl Write,
+) -> anyhow::Result<usize> {
+    let mut output = BufWriter::new(output);
+    let mut count = 0;
+    walk_repo(repo, mode, filter, cache, |_, res| {
+        serde_json::to_writer(&mut output, &res)?;
+        output.write_all(b"\n")?;
+        count += 1;
+        Ok(())
     })?;
-    Ok(IterResult {
-        commit: commit.id().to_string(),
-        podspecs,
-    })
+    output.flush()?;
+    Ok(count)
+}
+
+/// Like `iter_repo`, but only walks the tree to collect podspec blob ids, then
+/// reads and parses the blobs on the rayon pool. `git2` objects aren't `Send`,
+/// so each worker opens its own `Repository`.
+fn iter_repo_par(
+    repo: &str,
+    mode: WalkMode,
+    filter: Option<&GlobMatcher>,
+    cache: Option<&mut PodspecCache>,
+) -> anyhow::Result<IterResult> {
+    let repository = Repository::open(repo)?;
+    let commit = fetch_master(&repository)?;
+    let commits_by_path = commits_by_path(&repository, &commit, filter, cache);
+
+    let mut entries: Vec<(String, Oid)> = vec![];
+    for entry in PodspecIterMap::new(&repository, |_, path, entry| {
+        Ok((path.display().to_string(), entry.id()))
+    })? {
+        let (path, oid) = entry?;
+        if matches_filter(filter, &path) {
+            entries.push((path, oid));
+        }
+    }
+
+    println!("Parsing {} podspecs...", entries.len());
+    let parsed = entries
+        .par_iter()
+        .map_init(
+            || Repository::open(repo),
+            |repository, (path, oid)| {
+                let repository = repository.as_ref().map_err(|e| anyhow::anyhow!("{e}"))?;
+                let blob = repository.find_blob(*oid)?;
+                let parsed = parse_podspec(path, blob.content(), &commits_by_path);
+                for (_, res) in &parsed {
+                    mode.check(res)?;
+                }
+                Ok(parsed)
+            },
+        )
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut podspecs: BTreeMap<String, Vec<Res>> = BTreeMap::new();
+    for (name, res) in parsed.into_iter().flatten() {
+        podspecs.entry(name).or_default().push(res);
+    }
+    Ok(IterResult::new(repo, commit.id().to_string(), podspecs))
+}
+
+/// Orders versions dot segment by dot segment, numerically where both are
+/// numbers, so `1.10` comes after `1.9`.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.split('.'), b.split('.'));
+    loop {
+        let (a, b) = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => (a, b),
+        };
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Collapses each run of consecutive versions of a pod or subspec that share
+/// a byte-identical prepare command into its first podspec, with `versions`
+/// set to `first..last`. Errors are kept as they are, after the podspecs.
+fn collapse_versions(podspecs: &mut BTreeMap<String, Vec<Res>>) {
+    for results in podspecs.values_mut() {
+        let (mut parsed, errors): (Vec<_>, Vec<_>) = std::mem::take(results)
+            .into_iter()
+            .partition(|res| matches!(res, Res::Podspec(_)));
+        parsed.sort_by(|a, b| match (a, b) {
+            (Res::Podspec(a), Res::Podspec(b)) => a
+                .name
+                .cmp(&b.name)
+                .then_with(|| compare_versions(&a.version, &b.version)),
+            _ => Ordering::Equal,
+        });
+
+        let mut run: Option<(Podspec<'static>, String)> = None;
+        for res in parsed {
+            let Res::Podspec(podspec) = res else {
+                unreachable!()
+            };
+            if let Some((first, last)) = &mut run
+                && first.name == podspec.name
+                && first.prepare_command == podspec.prepare_command
+            {
+                *last = podspec.version.into_owned();
+                continue;
+            }
+            results.extend(run.take().map(finish_run));
+            let version = podspec.version.to_string();
+            run = Some((podspec, version));
+        }
+        results.extend(run.map(finish_run));
+        results.extend(errors);
+    }
+}
+
+fn finish_run((mut first, last): (Podspec<'static>, String)) -> Res {
+    if first.version != last {
+        first.versions = Some(format!("{}..{last}", first.version));
+    }
+    Res::Podspec(first)
+}
+
+type Changes = Vec<(Delta, String)>;
+
+/// How the aggregated `IterResult` is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    /// One row per podspec with a prepare command, for spreadsheets.
+    Csv,
+}
+
+/// Quotes `field` if it holds a comma, quote or line break, doubling any
+/// quotes, as RFC 4180 has it.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Writes a row per podspec in `res`, using the collapsed `first..last`
+/// range as the version where there is one. Podspecs that failed to parse
+/// have no prepare command to report, so they're left out.
+fn write_csv(res: &IterResult, output: impl Write) -> anyhow::Result<()> {
+    let mut output = BufWriter::new(output);
+    writeln!(
+        output,
+        "pod_name,version,loaded_from,prepare_command_kind,prepare_command_length"
+    )?;
+    for podspec in res.podspecs.values().flatten() {
+        let Res::Podspec(podspec) = podspec else {
+            continue;
+        };
+        let prepare_command = podspec.prepare_command.as_deref().unwrap_or_default();
+        let kind = podspec
+            .prepare_command_kind
+            .map(|kind| format!("{kind:?}"))
+            .unwrap_or_default();
+        writeln!(
+            output,
+            "{},{},{},{},{}",
+            csv_field(&podspec.name),
+            csv_field(podspec.versions.as_deref().unwrap_or(&podspec.version)),
+            csv_field(podspec.loaded_from.as_deref().unwrap_or_default()),
+            kind,
+            prepare_command.chars().count(),
+        )?;
+    }
+    output.flush()?;
+    Ok(())
 }
 
 fn tree_diff<'a>(
@@ -154,7 +660,7 @@ fn tree_diff<'a>(
     path: &str,
     lhs: Option<Tree<'a>>,
     rhs: Option<Tree<'a>>,
-) -> anyhow::Result<Vec<(Delta, String)>> {
+) -> anyhow::Result<Changes> {
     if lhs.as_ref().map(|t| t.id()) == rhs.as_ref().map(|t| t.id()) {
         return Ok(vec![]);
     }
@@ -162,14 +668,14 @@ fn tree_diff<'a>(
     let mut lhs_entries: Vec<(String, TreeEntry)> =
         lhs.as_ref().map_or_else(Default::default, |t| {
             t.iter()
-                .map(|e| (e.name().unwrap().to_string(), e))
+                .map(|e| (String::from_utf8_lossy(e.name_bytes()).into_owned(), e))
                 .collect()
         });
 
     let mut rhs_entries: Vec<(String, TreeEntry)> =
         rhs.as_ref().map_or_else(Default::default, |t| {
             t.iter()
-                .map(|e| (e.name().unwrap().to_string(), e))
+                .map(|e| (String::from_utf8_lossy(e.name_bytes()).into_owned(), e))
                 .collect()
         });
 
@@ -211,8 +717,10 @@ fn tree_diff<'a>(
                 let mut diff = tree_diff(
                     repository,
                     child_path.as_str(),
-                    l.map(|l| l.to_object(repository).unwrap().into_tree().unwrap()),
-                    r.map(|l| l.to_object(repository).unwrap().into_tree().unwrap()),
+                    l.map(|l| l.to_object(repository)?.peel_to_tree())
+                        .transpose()?,
+                    r.map(|r| r.to_object(repository)?.peel_to_tree())
+                        .transpose()?,
                 )?;
                 res.append(&mut diff);
             }
@@ -226,27 +734,153 @@ fn tree_diff<'a>(
     Ok(res)
 }
 
+/// Diffs `commit` against its first parent, so merges are attributed the way
+/// `git log --first-parent` would. The root commit is diffed against an empty
+/// tree, and has no parent to continue the walk from.
 fn thing<'a>(
     repository: &'a Repository,
     commit: &Commit<'a>,
-) -> anyhow::Result<(Commit<'a>, Vec<(Delta, String)>)> {
-    if commit.parent_count() != 1 {
-        bail!(
-            "Commit {} has {} parents\n{}",
-            commit.id(),
-            commit.parent_count(),
-            commit.body().unwrap_or_default()
+) -> anyhow::Result<(Option<Commit<'a>>, Changes)> {
+    let parent = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?),
+    };
+    let tree = commit.tree()?;
+
+    let parent_tree = parent.as_ref().map(|p| p.tree()).transpose()?;
+
+    Ok((parent, tree_diff(repository, ".", parent_tree, Some(tree))?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+enum PrepareCommandDelta {
+    Appeared,
+    Disappeared,
+    Modified,
+}
+
+impl PrepareCommandDelta {
+    /// How the prepare command changed from `before` to `after`, if at all.
+    fn between(before: &Option<String>, after: &Option<String>) -> Option<Self> {
+        match (before, after) {
+            (None, Some(_)) => Some(PrepareCommandDelta::Appeared),
+            (Some(_), None) => Some(PrepareCommandDelta::Disappeared),
+            (Some(before), Some(after)) if before != after => Some(PrepareCommandDelta::Modified),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PrepareCommandChange {
+    path: String,
+    change: PrepareCommandDelta,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+/// Prepare commands keyed by blob id. The same content shows up under many
+/// commits, so each blob only needs to be deserialized once. Only the
+/// prepare command is kept, not the whole podspec.
+#[derive(Default)]
+struct PodspecCache {
+    prepare_commands: HashMap<Oid, Option<Arc<str>>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl PodspecCache {
+    /// Returns the prepare command of the podspec stored in blob `oid`, or
+    /// `None` if it has none or doesn't parse.
+    fn prepare_command(
+        &mut self,
+        repository: &Repository,
+        oid: Oid,
+    ) -> anyhow::Result<Option<Arc<str>>> {
+        if let Some(prepare_command) = self.prepare_commands.get(&oid) {
+            self.hits += 1;
+            return Ok(prepare_command.clone());
+        }
+        self.misses += 1;
+        let blob = repository.find_blob(oid)?;
+        let prepare_command = serde_json::from_slice::<Podspec>(blob.content())
+            .ok()
+            .and_then(|podspec| podspec.prepare_command.as_deref().map(Arc::from));
+        self.prepare_commands.insert(oid, prepare_command.clone());
+        Ok(prepare_command)
+    }
+
+    fn report(&self) {
+        println!(
+            "Podspec cache: {} hits, {} misses ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
         );
     }
-    let parent = commit.parent(0)?;
-    let tree = commit.tree()?;
 
-    let parent_tree = parent.tree()?;
+    fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
 
-    Ok((
-        parent,
-        tree_diff(repository, ".", Some(parent_tree), Some(tree))?,
-    ))
+fn prepare_command_at(
+    repository: &Repository,
+    cache: &mut PodspecCache,
+    tree: &Tree<'_>,
+    path: &Path,
+) -> anyhow::Result<Option<String>> {
+    let entry = match tree.get_path(path) {
+        Ok(entry) => entry,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(cache
+        .prepare_command(repository, entry.id())?
+        .map(|prepare_command| prepare_command.to_string()))
+}
+
+/// Reports the podspecs whose prepare command appeared, disappeared, or
+/// changed between the `from` and `to` revisions.
+fn diff_repo(
+    repo: &str,
+    filter: Option<&GlobMatcher>,
+    from: &str,
+    to: &str,
+) -> anyhow::Result<Vec<PrepareCommandChange>> {
+    let repository = Repository::open(repo)?;
+    let from_tree = repository.revparse_single(from)?.peel_to_tree()?;
+    let to_tree = repository.revparse_single(to)?.peel_to_tree()?;
+
+    let mut cache = PodspecCache::default();
+    let mut changes = vec![];
+    for (_, path) in tree_diff(
+        &repository,
+        ".",
+        Some(from_tree.clone()),
+        Some(to_tree.clone()),
+    )? {
+        if !path.ends_with(".podspec.json") || !matches_filter(filter, &path) {
+            continue;
+        }
+        let path = path.trim_start_matches("./").to_string();
+        let before = prepare_command_at(&repository, &mut cache, &from_tree, Path::new(&path))?;
+        let after = prepare_command_at(&repository, &mut cache, &to_tree, Path::new(&path))?;
+        let Some(change) = PrepareCommandDelta::between(&before, &after) else {
+            continue;
+        };
+        changes.push(PrepareCommandChange {
+            path,
+            change,
+            before,
+            after,
+        });
+    }
+    changes.sort_by(|l, r| l.path.cmp(&r.path));
+    Ok(changes)
 }
 
 #[derive(Debug, Deserialize)]
@@ -282,16 +916,16 @@ fn get_dates(repo: &str) -> anyhow::Result<BTreeMap<String, Vec<(Delta, Oid)>>>
     let mut commit = branch.into_reference().peel_to_commit()?;
     let mut info: BTreeMap<String, Vec<(Delta, Oid)>> = BTreeMap::new();
     loop {
-        match commit.parent_count() {
-            0 => break,
-            1 => {}
-            n => bail!("Commit {} has {} parents", commit.id(), n),
-        }
-
-        let parent = commit.parent(0)?;
+        // Follow the first parent through merges, and diff the root commit
+        // against an empty tree so the initial additions are recorded.
+        let parent = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?),
+        };
+        let parent_tree = parent.as_ref().map(|p| p.tree()).transpose()?;
 
         let diff =
-            repository.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?;
+            repository.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
         for delta in diff.deltas() {
             let old = delta.old_file();
             let new = delta.new_file();
@@ -305,22 +939,28 @@ fn get_dates(repo: &str) -> anyhow::Result<BTreeMap<String, Vec<(Delta, Oid)>>>
                 .or_default()
                 .push((delta.status(), commit.id()));
         }
+        let Some(parent) = parent else {
+            break;
+        };
         commit = parent;
     }
     Ok(info)
 }
 
+type PodspecFn<'repo, T> =
+    Box<dyn Fn(&'repo Repository, &Path, TreeEntry) -> Result<T, git2::Error> + 'static>;
+
 struct PodspecIterMap<'repo, T> {
     repository: &'repo Repository,
     iter: Option<(Tree<'repo>, PathBuf, Range<usize>)>,
     stack: Vec<(Tree<'repo>, PathBuf)>,
-    func: Box<dyn Fn(&'repo Repository, &Path, TreeEntry) -> T + 'static>,
+    func: PodspecFn<'repo, T>,
 }
 
 impl<'repo, T> PodspecIterMap<'repo, T> {
     fn new<F>(repository: &'repo Repository, func: F) -> Result<Self, git2::Error>
     where
-        F: Fn(&'repo Repository, &Path, TreeEntry) -> T + 'static,
+        F: Fn(&'repo Repository, &Path, TreeEntry) -> Result<T, git2::Error> + 'static,
     {
         let branch = repository.find_branch("origin/master", git2::BranchType::Remote)?;
         let commit = branch.get().peel_to_commit()?;
@@ -337,31 +977,39 @@ impl<'repo, T> PodspecIterMap<'repo, T> {
 }
 
 impl<T> Iterator for PodspecIterMap<'_, T> {
-    type Item = T;
+    type Item = Result<T, git2::Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            if let Some((tree, dir, range)) = self.iter.as_mut() {
-                if let Some(entry) = (*range).next().map(|i| tree.get(i).unwrap()) {
-                    let dir = dir.join(entry.name().unwrap());
-                    match entry.kind() {
-                        Some(ObjectType::Tree) => {
-                            let tree = entry
-                                .to_object(self.repository)
-                                .unwrap()
-                                .into_tree()
-                                .unwrap();
-                            self.stack.push((tree, dir));
-                            continue;
-                        }
-                        Some(ObjectType::Blob)
-                            if entry.name().unwrap().ends_with(".podspec.json") =>
+            if let Some((tree, dir, range)) = self.iter.as_mut()
+                && let Some(i) = range.next()
+            {
+                let Some(entry) = tree.get(i) else {
+                    return Some(Err(git2::Error::from_str(&format!(
+                        "missing entry {} in tree {}",
+                        i,
+                        dir.display()
+                    ))));
+                };
+                let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+                let dir = dir.join(&name);
+                match entry.kind() {
+                    Some(ObjectType::Tree) => {
+                        let tree = match entry
+                            .to_object(self.repository)
+                            .and_then(|object| object.peel_to_tree())
                         {
-                            return Some((self.func)(self.repository, &dir, entry));
-                        }
-                        _ => {
-                            continue;
-                        }
+                            Ok(tree) => tree,
+                            Err(e) => return Some(Err(e)),
+                        };
+                        self.stack.push((tree, dir));
+                        continue;
+                    }
+                    Some(ObjectType::Blob) if name.ends_with(".podspec.json") => {
+                        return Some((self.func)(self.repository, &dir, entry));
+                    }
+                    _ => {
+                        continue;
                     }
                 }
             }
@@ -372,39 +1020,466 @@ impl<T> Iterator for PodspecIterMap<'_, T> {
     }
 }
 
+fn print_usage() {
+    eprintln!(
+        "Usage: cocoapods-prepare-commands [--aggregate] [--no-collapse] [--fail-fast] [--prepare-command-history] [--filter GLOB] [--format json|csv] [SPECS_REPO] [OUTPUT] [FROM..TO]"
+    );
+    eprintln!("SPECS_REPO defaults to $CP_SPECS_REPO, then the current directory.");
+    eprintln!("With FROM..TO, only prepare commands changed between the two refs are written.");
+    eprintln!(
+        "With --aggregate or CP_AGGREGATE, a single JSON document grouped by pod is written."
+    );
+    eprintln!(
+        "That document collapses versions sharing a prepare command, unless --no-collapse is given."
+    );
+    eprintln!("With --filter, only pods whose name matches GLOB are read.");
+    eprintln!(
+        "With --prepare-command-history or CP_PREPARE_COMMAND_HISTORY, each commit notes how it changed the prepare command."
+    );
+    eprintln!("With --format csv, the aggregated podspecs are written as CSV rows.");
+    eprintln!(
+        "With --fail-fast or CP_FAIL_FAST, the first podspec that fails to parse is reported and the exit status is 1."
+    );
+}
+
+/// Unwraps `result`, or reports the error and exits with status 1, so a
+/// failed walk doesn't end in a panic.
+fn or_exit<T>(result: anyhow::Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {e:#}");
+        std::process::exit(1);
+    })
+}
+
 fn main() {
-    let repo = "/Users/segiddins/Development/github.com/cocoapods/Specs";
-
-    let repository = Repository::open(repo).unwrap();
-    let iter = PodspecIterMap::new(&repository, |repo, path, entry| {
-        let binding = entry.to_object(repo).unwrap();
-        let blob = binding.into_blob().unwrap();
-        let podspec: Res = serde_json::from_slice(blob.content())
-            .map(|podspec: Podspec| {
-                if podspec.prepare_command.is_some() {
-                    Res::Podspec(podspec.into_owned())
-                } else {
-                    Res::NoPrepareCommand
+    let mut aggregate = std::env::var_os("CP_AGGREGATE").is_some();
+    let mut collapse = true;
+    let mut fail_fast = std::env::var_os("CP_FAIL_FAST").is_some();
+    let mut history = std::env::var_os("CP_PREPARE_COMMAND_HISTORY").is_some();
+    let mut filter = None;
+    let mut format = OutputFormat::Json;
+    let mut args = vec![];
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--aggregate" => aggregate = true,
+            "--no-collapse" => collapse = false,
+            "--fail-fast" => fail_fast = true,
+            "--prepare-command-history" => history = true,
+            "--filter" => {
+                let Some(pattern) = argv.next() else {
+                    eprintln!("--filter expects a glob, such as 'Firebase*'");
+                    std::process::exit(1);
+                };
+                match Glob::new(&pattern) {
+                    Ok(glob) => filter = Some(glob.compile_matcher()),
+                    Err(e) => {
+                        eprintln!("Invalid --filter glob {pattern:?}: {e}");
+                        std::process::exit(1);
+                    }
                 }
-            })
-            .unwrap_or_else(|e| Res::Error {
-                error: e.to_string(),
-                path: path.display().to_string(),
-            });
-        podspec
-    })
-    .unwrap();
-    // println!("{:#?}", iter.collect::<Vec<_>>().len());
+            }
+            "--format" => match argv.next().as_deref() {
+                Some("json") => format = OutputFormat::Json,
+                Some("csv") => format = OutputFormat::Csv,
+                other => {
+                    eprintln!("--format expects json or csv, got {other:?}");
+                    std::process::exit(1);
+                }
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown flag {flag}");
+                print_usage();
+                std::process::exit(1);
+            }
+            _ => args.push(arg),
+        }
+    }
+    let filter = filter.as_ref();
+    let mut args = args.into_iter();
+    let repo = args
+        .next()
+        .or_else(|| std::env::var("CP_SPECS_REPO").ok())
+        .unwrap_or_else(|| ".".to_string());
+    // CSV rows are written from the aggregated result.
+    let aggregate = aggregate || format == OutputFormat::Csv;
+    let output = args.next().unwrap_or_else(|| {
+        match (format, aggregate) {
+            (OutputFormat::Csv, _) => "podspecs_with_prepare_commands.csv",
+            (OutputFormat::Json, true) => "podspecs_with_prepare_commands.json",
+            (OutputFormat::Json, false) => "podspecs_with_prepare_commands.jsonl",
+        }
+        .to_string()
+    });
+    let range = args.next();
+    let mode = if fail_fast {
+        WalkMode::FailFast
+    } else {
+        WalkMode::CollectErrors
+    };
+
+    if let Err(e) = Repository::open(&repo) {
+        eprintln!("{repo} is not a git repository: {}", e.message());
+        print_usage();
+        std::process::exit(1);
+    }
 
-    let mut res = iter_repo(repo).unwrap();
+    if let Some(range) = range {
+        if format == OutputFormat::Csv {
+            eprintln!("--format csv isn't supported with a FROM..TO range");
+            std::process::exit(1);
+        }
+        let Some((from, to)) = range.split_once("..") else {
+            eprintln!("Expected a FROM..TO range, got {range}");
+            std::process::exit(1);
+        };
+        let changes = or_exit(diff_repo(&repo, filter, from, to));
+        println!("{} prepare commands changed in {range}", changes.len());
+        let file = File::create(&output).unwrap();
+        serde_json::to_writer_pretty(file, &changes).unwrap();
+        return;
+    }
+    let mut cache = history.then(PodspecCache::default);
+    let start = std::time::Instant::now();
+    if !aggregate {
+        let file = File::create(&output).unwrap();
+        let count = or_exit(stream_repo(&repo, mode, filter, cache.as_mut(), file));
+        println!("Streamed {count} podspecs in {:?}", start.elapsed());
+        cache.iter().for_each(PodspecCache::report);
+        return;
+    }
+    let mut res = if std::env::var_os("CP_SERIAL").is_some() {
+        or_exit(iter_repo(&repo, mode, filter, cache.as_mut()))
+    } else {
+        or_exit(iter_repo_par(&repo, mode, filter, cache.as_mut()))
+    };
+    println!("Collected podspecs in {:?}", start.elapsed());
+    cache.iter().for_each(PodspecCache::report);
     res.podspecs.values_mut().for_each(|v| {
         v.sort_by_key(|res| match res {
             Res::Podspec(podspec) => podspec.loaded_from.to_owned().unwrap(),
             Res::Error { error: _, path } => path.to_owned(),
-            _ => unreachable!(),
         });
     });
+    if collapse {
+        collapse_versions(&mut res.podspecs);
+    }
+
+    let file = File::create(&output).unwrap();
+    match format {
+        OutputFormat::Json => serde_json::to_writer_pretty(file, &res).unwrap(),
+        OutputFormat::Csv => write_csv(&res, file).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commits exactly `files`, as `(path, contents)`, on top of `parents`,
+    /// at `seconds` past the epoch.
+    fn commit(
+        repository: &Repository,
+        files: &[(&str, &str)],
+        parents: &[Oid],
+        seconds: i64,
+    ) -> Oid {
+        let workdir = repository.workdir().unwrap();
+        let mut index = repository.index().unwrap();
+        index.clear().unwrap();
+        for (path, contents) in files {
+            let full = workdir.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, contents).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature =
+            git2::Signature::new("Jane", "jane@example.com", &git2::Time::new(seconds, 0)).unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|oid| repository.find_commit(*oid).unwrap())
+            .collect();
+        repository
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "commit",
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+    }
+
+    const A: (&str, &str) = ("Specs/A/1.0/A.podspec.json", r#"{"name":"A"}"#);
+    const B: (&str, &str) = ("Specs/B/1.0/B.podspec.json", r#"{"name":"B"}"#);
+    const C: (&str, &str) = ("Specs/C/1.0/C.podspec.json", r#"{"name":"C"}"#);
+
+    /// A repo where `B` is added on a side branch that's merged into master,
+    /// after master added `C`. Returns the root, master and merge commits.
+    fn merged_repo() -> (tempfile::TempDir, Oid, Oid, Oid) {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let root = commit(&repository, &[A], &[], 1_000);
+        let side = commit(&repository, &[A, B], &[root], 2_000);
+        let master = commit(&repository, &[A, C], &[root], 3_000);
+        let merge = commit(&repository, &[A, B, C], &[master, side], 4_000);
+        repository
+            .reference("refs/remotes/origin/master", merge, true, "test")
+            .unwrap();
+        (dir, root, master, merge)
+    }
+
+    #[test]
+    fn commits_by_path_follows_first_parent_through_merges() {
+        let (dir, root, master, merge) = merged_repo();
+        let repository = Repository::open(dir.path()).unwrap();
+        let commits = commits_by_path(
+            &repository,
+            &repository.find_commit(merge).unwrap(),
+            None,
+            None,
+        );
+
+        let attributed = |path: &str| {
+            commits[&format!("./{path}")]
+                .iter()
+                .map(|(delta, date, oid, _)| (*delta, date.timestamp(), *oid))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(attributed(A.0), [(Delta::Added, 1_000, root)]);
+        assert_eq!(attributed(B.0), [(Delta::Added, 4_000, merge)]);
+        assert_eq!(attributed(C.0), [(Delta::Added, 3_000, master)]);
+        // Without a cache, no podspec is read to trace its prepare command.
+        assert!(
+            commits
+                .values()
+                .flatten()
+                .all(|(.., change)| change.is_none())
+        );
+    }
+
+    #[test]
+    fn commits_by_path_parses_each_blob_once() {
+        const X: &str = "Specs/X/1.0/X.podspec.json";
+        const MAKE: &str = r#"{"name":"X","prepare_command":"make"}"#;
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let root = commit(&repository, &[(X, MAKE)], &[], 1_000);
+        let modified = commit(
+            &repository,
+            &[(X, r#"{"name":"X","prepare_command":"make all"}"#)],
+            &[root],
+            2_000,
+        );
+        // Reverting brings back the root's blob under a third commit.
+        let reverted = commit(&repository, &[(X, MAKE)], &[modified], 3_000);
+        let summary = commit(
+            &repository,
+            &[(X, r#"{"name":"X","summary":"X","prepare_command":"make"}"#)],
+            &[reverted],
+            4_000,
+        );
 
-    let file = File::create("podspecs_with_prepare_commands.json").unwrap();
-    serde_json::to_writer_pretty(file, &res).unwrap();
+        let mut cache = PodspecCache::default();
+        let commits = commits_by_path(
+            &repository,
+            &repository.find_commit(summary).unwrap(),
+            None,
+            Some(&mut cache),
+        );
+        let changes: Vec<_> = commits[&format!("./{X}")]
+            .iter()
+            .map(|(delta, _, oid, change)| (*delta, *oid, *change))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Delta::Modified, summary, None),
+                (
+                    Delta::Modified,
+                    reverted,
+                    Some(PrepareCommandDelta::Modified)
+                ),
+                (
+                    Delta::Modified,
+                    modified,
+                    Some(PrepareCommandDelta::Modified)
+                ),
+                (Delta::Added, root, Some(PrepareCommandDelta::Appeared)),
+            ]
+        );
+        // Three distinct blobs are parsed once each; every other lookup,
+        // including the reverted blob, is a hit.
+        assert_eq!((cache.misses, cache.hits), (3, 4));
+        assert_eq!(cache.prepare_commands.len(), 3);
+    }
+
+    #[test]
+    fn diff_repo_reports_prepare_command_changes() {
+        const X: &str = "Specs/X/1.0/X.podspec.json";
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let before = commit(
+            &repository,
+            &[A, (X, r#"{"name":"X","prepare_command":"make"}"#)],
+            &[],
+            1_000,
+        );
+        let after = commit(
+            &repository,
+            &[B, (X, r#"{"name":"X","prepare_command":"make all"}"#)],
+            &[before],
+            2_000,
+        );
+
+        let changes = diff_repo(
+            dir.path().to_str().unwrap(),
+            None,
+            &before.to_string(),
+            &after.to_string(),
+        )
+        .unwrap();
+        let changes: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.path.as_str(),
+                    c.change,
+                    c.before.as_deref(),
+                    c.after.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [(
+                X,
+                PrepareCommandDelta::Modified,
+                Some("make"),
+                Some("make all")
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_podspec_reports_invalid_utf8() {
+        let path = "Specs/X/1.0/X.podspec.json";
+        let parsed = parse_podspec(path, b"{\"name\":\"\xff\"}", &CommitsByPath::new());
+        let [
+            (
+                pod,
+                Res::Error {
+                    error,
+                    path: loaded_from,
+                },
+            ),
+        ] = parsed.as_slice()
+        else {
+            panic!("expected a single error, got {parsed:?}");
+        };
+        assert_eq!(pod, "X");
+        assert_eq!(error, "invalid UTF-8 at byte 9");
+        assert_eq!(loaded_from, path);
+
+        // Valid UTF-8 that isn't JSON keeps serde's message.
+        let parsed = parse_podspec(path, b"{", &CommitsByPath::new());
+        let [(_, Res::Error { error, .. })] = parsed.as_slice() else {
+            panic!("expected a single error, got {parsed:?}");
+        };
+        assert!(error.starts_with("EOF while parsing"), "{error}");
+    }
+
+    #[test]
+    fn podspec_iter_map_yields_closure_results() {
+        let (dir, ..) = merged_repo();
+        let repository = Repository::open(dir.path()).unwrap();
+        let iter = PodspecIterMap::new(&repository, |repository, path, entry| {
+            let blob = entry.to_object(repository)?.peel_to_blob()?;
+            Ok((path.to_path_buf(), blob.content().to_vec()))
+        })
+        .unwrap();
+
+        let mut podspecs = iter.collect::<Result<Vec<_>, _>>().unwrap();
+        podspecs.sort();
+        assert_eq!(
+            podspecs,
+            [A, B, C].map(|(path, contents)| (PathBuf::from(path), contents.as_bytes().to_vec()))
+        );
+
+        // Errors from the closure are yielded rather than ending the walk.
+        let iter = PodspecIterMap::<()>::new(&repository, |_, path, _| {
+            Err(git2::Error::from_str(&path.display().to_string()))
+        })
+        .unwrap();
+        assert_eq!(iter.filter(Result::is_err).count(), 3);
+    }
+
+    /// A repo whose master adds a podspec with a non-UTF-8 name next to `A`,
+    /// set up as its own `origin` so `walk_repo` can fetch it.
+    fn non_utf8_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let root = commit(&repository, &[A], &[], 1_000);
+        let root = repository.find_commit(root).unwrap();
+
+        let blob = repository
+            .blob(br#"{"name":"X","prepare_command":"make"}"#)
+            .unwrap();
+        let mut builder = repository.treebuilder(Some(&root.tree().unwrap())).unwrap();
+        builder
+            .insert(b"X\xff.podspec.json".to_vec(), blob, 0o100644)
+            .unwrap();
+        let tree = repository.find_tree(builder.write().unwrap()).unwrap();
+        let signature =
+            git2::Signature::new("Jane", "jane@example.com", &git2::Time::new(2_000, 0)).unwrap();
+        let master = repository
+            .commit(
+                Some("refs/heads/master"),
+                &signature,
+                &signature,
+                "commit",
+                &tree,
+                &[&root],
+            )
+            .unwrap();
+        repository
+            .reference("refs/remotes/origin/master", master, true, "test")
+            .unwrap();
+        repository
+            .remote("origin", dir.path().to_str().unwrap())
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn walk_repo_reports_unreadable_podspecs() {
+        let dir = non_utf8_repo();
+        let repo = dir.path().to_str().unwrap();
+
+        let res = iter_repo(repo, WalkMode::CollectErrors, None, None).unwrap();
+        let results: Vec<_> = res.podspecs.values().flatten().collect();
+        let [Res::Error { error, path }] = results.as_slice() else {
+            panic!("expected a single error, got {results:?}");
+        };
+        assert_eq!(error, "file name isn't valid UTF-8");
+        assert_eq!(path, "X\u{fffd}.podspec.json");
+
+        let error = iter_repo(repo, WalkMode::FailFast, None, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to parse X\u{fffd}.podspec.json: file name isn't valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn get_dates_follows_first_parent_through_merges() {
+        let (dir, root, master, merge) = merged_repo();
+        let dates = get_dates(dir.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(dates[A.0], [(Delta::Added, root)]);
+        assert_eq!(dates[B.0], [(Delta::Added, merge)]);
+        assert_eq!(dates[C.0], [(Delta::Added, master)]);
+    }
 }
@@ -318,9 +318,9 @@ where
         match (state, event?.0) {
             (State::Key, Event::MappingEnd) => {
                 // End of the mapping
-                return version
+                return Ok(version
                     .ok_or_else(|| anyhow::anyhow!("Expected version"))?
-                    .parse();
+                    .parse()?);
             }
 
             (State::Key, event) => match parse_str(event)?.as_ref() {
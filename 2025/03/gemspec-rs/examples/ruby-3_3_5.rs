@@ -1,8 +1,11 @@
 use std::{fs::File, io::Read, os::unix::fs::MetadataExt, sync::atomic::AtomicU32};
 
-use anyhow::Result;
-use gemspec_rs::gem::{Package, PackageEntry};
-use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use anyhow::{Context, Result};
+use gemspec_rs::gem::{
+    PackageEntry, entry_path,
+    magic::{DefaultMagicDetector, MagicDetector},
+    process_cache_dir, sha256_file,
+};
 use sha2::Digest;
 use std::sync::atomic::Ordering::SeqCst;
 
@@ -25,78 +28,68 @@ fn main() -> Result<()> {
 
     let count: AtomicU32 = 0.into();
 
-    cache
-        .read_dir()?
-        .filter_map(|entry| {
-            let path = entry.unwrap().path();
-            if path.extension().is_some_and(|ext| ext == "gem") {
-                Some(path)
-            } else {
-                None
-            }
-        })
-        .par_bridge()
-        .into_par_iter()
-        .for_each(|path| {
-            let file = File::open(&path).unwrap();
-            count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            let mut package = Package::new(file);
-
-            let spec = match package.specification() {
-                Ok(spec) => spec,
-                Err(err) => {
-                    eprintln!(
-                        "Failed to read specification {:?}: {:#?}",
-                        path.display(),
-                        err
-                    );
-                    return;
+    let failures = process_cache_dir(cache, 8, |path, package| {
+        let sha256 = sha256_file(File::open(path)?)?;
+        println!("{}  {}", sha256, path.display());
+
+        count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        let spec = match package.specification() {
+            Ok(spec) => spec,
+            Err(err) => {
+                if let Ok(partial) = package.partial_specification() {
+                    eprintln!("  recovered {partial:?}");
                 }
+                return Err(err).context("Failed to read specification");
+            }
+        };
+
+        // let mut summary = GemSummary {
+        //     name: spec.name.clone(),
+        //     version: spec.version.to_string(),
+        //     platform: spec.platform.to_string(),
+        //     size: file.metadata()?.size(),
+        //     metadata_sha256: String::new(),
+        //     files: 0,
+        //     sha256,
+        //     source_date_epoch: package.source_date_epoch()?.unwrap_or_default(),
+        //     rubygems_version: String::new(),
+        // };
+
+        package.each_entry(|e| {
+            let mut buf = Vec::new();
+            e.read_to_end(&mut buf)?;
+
+            let sha256 = sha2::Sha256::digest(&buf);
+
+            let path = entry_path(e)?;
+            let header = e.header();
+            let link_name = header.link_name()?;
+            let magic = DefaultMagicDetector::default().detect(&buf, &path);
+
+            let entry = PackageEntry {
+                gem: spec.name.as_str(),
+                version: spec.version.as_str(),
+                platform: spec.platform.as_str(),
+                size: header.size()?,
+                path: &path,
+                link_name: link_name.as_ref().map(|s| s.to_str().unwrap()),
+                mode: header.mode()?,
+                uid: header.uid()?,
+                gid: header.gid()?,
+                mtime: header.mtime()?,
+                sha256,
+                magic,
             };
+            // println!("{}", serde_json::to_string(&entry).unwrap());
+            Ok(())
+        })?;
+        Ok(())
+    })?;
 
-            // let mut summary = GemSummary {
-            //     name: spec.name.clone(),
-            //     version: spec.version.to_string(),
-            //     platform: spec.platform.to_string(),
-            //     size: file.metadata()?.size(),
-            //     metadata_sha256: String::new(),
-            //     files: 0,
-            //     sha256: String::new(),
-            //     source_date_epoch: 0,
-            //     rubygems_version: String::new(),
-            // };
-
-            package
-                .each_entry(|e| {
-                    let mut buf = Vec::new();
-                    e.read_to_end(&mut buf)?;
-
-                    let sha256 = sha2::Sha256::digest(&buf);
-                    let magic = tree_magic_mini::from_u8(&buf);
-
-                    let header = e.header();
-                    let path = header.path().unwrap();
-                    let link_name = header.link_name()?;
-
-                    let entry = PackageEntry {
-                        gem: spec.name.as_str(),
-                        version: spec.version.as_str(),
-                        platform: spec.platform.as_str(),
-                        size: header.size()?,
-                        path: path.to_str().unwrap(),
-                        link_name: link_name.as_ref().map(|s| s.to_str().unwrap()),
-                        mode: header.mode()?,
-                        uid: header.uid()?,
-                        gid: header.gid()?,
-                        mtime: header.mtime()?,
-                        sha256,
-                        magic,
-                    };
-                    // println!("{}", serde_json::to_string(&entry).unwrap());
-                    Ok(())
-                })
-                .unwrap();
-        });
+    for (path, err) in &failures {
+        eprintln!("Failed to process {:?}: {:#?}", path.display(), err);
+    }
 
     println!("Processed {} gem files", count.load(SeqCst));
 
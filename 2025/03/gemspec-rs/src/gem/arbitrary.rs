@@ -0,0 +1,99 @@
+//! `proptest` generators for versions and requirements, behind the
+//! `proptest` feature.
+
+use std::cmp::Ordering;
+
+use proptest::{collection::vec, prelude::*};
+
+use super::{Requirement, RequirementOperator, Version, VersionSegment};
+
+/// Valid versions: one to four numeric release segments, sometimes
+/// followed by a prerelease that starts with a short lowercase tag and may
+/// go on with more tags or numbers, like `1.2.0.rc.1`.
+impl Arbitrary for Version {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Version>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        let number = prop_oneof![3 => 0..10u64, 1 => 0..1000u64];
+        let tag = "[a-z]{1,3}";
+        let release = vec(number.clone(), 1..=4);
+        let prerelease = proptest::option::weighted(
+            0.3,
+            (
+                tag,
+                vec(
+                    prop_oneof![
+                        number.prop_map(VersionSegment::Number),
+                        tag.prop_map(VersionSegment::String),
+                    ],
+                    0..=2,
+                ),
+            ),
+        );
+        (release, prerelease)
+            .prop_map(|(release, prerelease)| {
+                let mut segments: Vec<_> =
+                    release.into_iter().map(VersionSegment::Number).collect();
+                if let Some((tag, rest)) = prerelease {
+                    segments.push(VersionSegment::String(tag));
+                    segments.extend(rest);
+                }
+                Version::from_segments(segments)
+            })
+            .boxed()
+    }
+}
+
+/// Satisfiable requirements: one to three constraints that all hold for
+/// some arbitrary version, as generated by `satisfied_by`.
+impl Arbitrary for Requirement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Requirement>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        any::<Version>().prop_flat_map(satisfied_by).boxed()
+    }
+}
+
+/// Requirements of one to three constraints that `version` satisfies, for
+/// properties that need the witness along with the requirement.
+pub fn satisfied_by(version: Version) -> BoxedStrategy<Requirement> {
+    vec(constraint_satisfied_by(version), 1..=3)
+        .prop_map(Requirement::new)
+        .boxed()
+}
+
+/// A single constraint `version` satisfies: a bound derived from another
+/// arbitrary version, with an operator chosen by how the two compare, or a
+/// `~>` on a prefix of `version`'s release.
+fn constraint_satisfied_by(version: Version) -> BoxedStrategy<(RequirementOperator, Version)> {
+    use RequirementOperator::*;
+
+    let bound = {
+        let version = version.clone();
+        any::<Version>()
+            .prop_flat_map(move |other| {
+                let ops = match version.cmp(&other) {
+                    Ordering::Less => vec![LessThan, LessThanOrEqual, NotEqual],
+                    Ordering::Greater => vec![GreaterThan, GreaterThanOrEqual, NotEqual],
+                    Ordering::Equal => vec![Equal, GreaterThanOrEqual, LessThanOrEqual],
+                };
+                (proptest::sample::select(ops), Just(other))
+            })
+            .boxed()
+    };
+
+    // `~> x` needs `version >= x`. A release is at least each of its
+    // prefixes, but a prerelease can fall below one, as `0.0.a < 0` does.
+    let release = version.release_segments().to_vec();
+    let prefixes: Vec<Version> = (1..=release.len())
+        .map(|len| Version::from_segments(release[..len].to_vec()))
+        .filter(|prefix| version >= *prefix)
+        .collect();
+    if prefixes.is_empty() {
+        return prop_oneof![Just((Equal, version)), bound].boxed();
+    }
+    let tilde = proptest::sample::select(prefixes).prop_map(|prefix| (Tilde, prefix));
+    prop_oneof![1 => Just((Equal, version)), 1 => tilde, 4 => bound].boxed()
+}
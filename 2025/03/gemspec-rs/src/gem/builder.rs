@@ -0,0 +1,91 @@
+//! Writes `.gem` files, the counterpart to reading them with `Package`.
+
+use std::io::Write;
+
+use flate2::{Compression, write::GzEncoder};
+
+use super::{Error, Specification, hex_digest, normalize_entry_path};
+
+/// Builds a gem the way `gem build` lays it out: an outer tar holding
+/// `metadata.gz`, `data.tar.gz` and `checksums.yaml.gz`. Files are gzipped
+/// into `data.tar.gz` as they're added, so only the compressed data is kept
+/// in memory.
+///
+/// The metadata is the specification as its `Serialize` impl writes it,
+/// which `Package` reads back, but it lacks the `!ruby/object` tags that
+/// `Gem::Package` expects.
+pub struct GemWriter {
+    data: tar::Builder<GzEncoder<Vec<u8>>>,
+}
+
+impl Default for GemWriter {
+    fn default() -> Self {
+        GemWriter::new()
+    }
+}
+
+impl GemWriter {
+    pub fn new() -> Self {
+        GemWriter {
+            data: tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default())),
+        }
+    }
+
+    /// Adds a file to `data.tar.gz` under `path`, normalized as
+    /// `normalize_entry_path` does, so paths that would escape the install
+    /// directory fail with `Error::UnsafePath`.
+    pub fn add_file(&mut self, path: &str, bytes: &[u8], mode: u32) -> Result<(), Error> {
+        let path = normalize_entry_path(path)?;
+        if path.is_empty() {
+            return Err(Error::UnsafePath(path));
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(mode);
+        self.data.append_data(&mut header, path, bytes)?;
+        Ok(())
+    }
+
+    /// Writes the gem for `spec` to `out`. Every member is stamped with
+    /// `spec.date`, so building the same files twice gives the same bytes.
+    /// `spec.files` isn't checked against the added files; use
+    /// `Package::validate_manifest` on the result for that.
+    pub fn finish(self, spec: &Specification, out: impl Write) -> Result<(), Error> {
+        let mtime = spec.date.timestamp().max(0) as u64;
+        let data = self.data.into_inner()?.finish()?;
+        let metadata = gzip(serde_yaml::to_string(spec)?.as_bytes())?;
+
+        let mut checksums = String::from("---\n");
+        for (algorithm, digest) in [
+            ("SHA256", hex_digest::<sha2::Sha256> as fn(&[u8]) -> String),
+            ("SHA512", hex_digest::<sha2::Sha512>),
+        ] {
+            checksums.push_str(&format!("{algorithm}:\n"));
+            for (member, bytes) in [("metadata.gz", &metadata), ("data.tar.gz", &data)] {
+                checksums.push_str(&format!("  {member}: {}\n", digest(bytes)));
+            }
+        }
+        let checksums = gzip(checksums.as_bytes())?;
+
+        let mut gem = tar::Builder::new(out);
+        for (member, bytes) in [
+            ("metadata.gz", metadata),
+            ("data.tar.gz", data),
+            ("checksums.yaml.gz", checksums),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o444);
+            header.set_mtime(mtime);
+            gem.append_data(&mut header, member, bytes.as_slice())?;
+        }
+        gem.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
@@ -0,0 +1,37 @@
+/// Errors returned by the `gem` API. Binaries can still use `anyhow`, since
+/// this converts into `anyhow::Error` with `?`.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("gem is missing metadata.gz")]
+    MissingMetadata,
+    #[error("gem is missing data.tar.gz")]
+    MissingData,
+    #[error("failed to parse YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{member} checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        member: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid version: {0}")]
+    VersionParse(String),
+    #[error("invalid platform: {0}")]
+    PlatformParse(String),
+    #[error("invalid dependency type: {0}")]
+    DependencyType(String),
+    #[error("unsupported requirement: {0}")]
+    Requirement(String),
+    #[error("invalid specification: {0}")]
+    Specification(String),
+    #[error("invalid Marshal data: {0}")]
+    Marshal(String),
+    #[error("failed to build thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[error("{member} is larger than the {limit} byte limit")]
+    DecompressedTooLarge { member: String, limit: u64 },
+    #[error("unsafe path in data.tar.gz: {0}")]
+    UnsafePath(String),
+}
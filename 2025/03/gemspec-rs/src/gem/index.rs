@@ -0,0 +1,63 @@
+use std::{io::Read, str::FromStr};
+
+use anyhow::{Context, bail};
+use flate2::read::GzDecoder;
+
+use super::{
+    Error, Platform, Version,
+    marshal::{MarshalReader, Value},
+};
+
+/// Parses a `specs.4.8.gz`, `latest_specs.4.8.gz` or `prerelease_specs.4.8.gz`
+/// index into its `[name, Gem::Version, platform]` tuples.
+pub fn parse_specs_gz<R: Read>(r: R) -> Result<Vec<(String, Version, Platform)>, Error> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(r).read_to_end(&mut bytes)?;
+    parse_specs(&bytes)
+}
+
+/// Parses an uncompressed `specs.4.8` index.
+pub fn parse_specs(bytes: &[u8]) -> Result<Vec<(String, Version, Platform)>, Error> {
+    read_specs(bytes).map_err(|e| Error::Marshal(format!("{e:#}")))
+}
+
+fn read_specs(bytes: &[u8]) -> anyhow::Result<Vec<(String, Version, Platform)>> {
+    let Value::Array(specs) = MarshalReader::load(bytes)? else {
+        bail!("Expected an array of specs");
+    };
+    specs
+        .into_iter()
+        .enumerate()
+        .map(|(idx, spec)| {
+            let Value::Array(tuple) = spec else {
+                bail!("Expected spec {idx} to be an array");
+            };
+            let [name, version, platform] = <[Value; 3]>::try_from(tuple).map_err(|tuple| {
+                anyhow::anyhow!(
+                    "Expected spec {idx} to have 3 elements, got {}",
+                    tuple.len()
+                )
+            })?;
+            let name = name
+                .into_string()
+                .with_context(|| format!("spec {idx} name"))?;
+            let version = match version {
+                Value::UserMarshal(class, data) if class == "Gem::Version" => match *data {
+                    Value::Array(data) => data
+                        .into_iter()
+                        .next()
+                        .context("empty Gem::Version")?
+                        .into_string()?,
+                    _ => bail!("Expected Gem::Version data to be an array"),
+                },
+                _ => bail!("Expected spec {idx} version to be a Gem::Version"),
+            };
+            let version = Version::from_str(&version)
+                .with_context(|| format!("spec {idx} ({name}) version"))?;
+            let platform = platform
+                .into_string()
+                .with_context(|| format!("spec {idx} ({name}) platform"))?;
+            Ok((name, version, Platform::new(platform)))
+        })
+        .collect()
+}
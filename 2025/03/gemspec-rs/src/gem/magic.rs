@@ -0,0 +1,76 @@
+use std::path::Path;
+
+/// Detects the content type recorded in `PackageEntry::magic`.
+pub trait MagicDetector {
+    fn detect(&self, bytes: &[u8], path: &str) -> String;
+}
+
+/// `tree_magic_mini` when the `tree_magic` feature is enabled, otherwise
+/// `ExtensionMagicDetector`.
+#[cfg(feature = "tree_magic")]
+pub type DefaultMagicDetector = TreeMagicDetector;
+#[cfg(not(feature = "tree_magic"))]
+pub type DefaultMagicDetector = ExtensionMagicDetector;
+
+/// Sniffs the contents with `tree_magic_mini`, ignoring the path.
+#[cfg(feature = "tree_magic")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeMagicDetector;
+
+#[cfg(feature = "tree_magic")]
+impl MagicDetector for TreeMagicDetector {
+    fn detect(&self, bytes: &[u8], _path: &str) -> String {
+        tree_magic_mini::from_u8(bytes).to_string()
+    }
+}
+
+/// Guesses from the file extension alone, for the file types that commonly
+/// ship in gems.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtensionMagicDetector;
+
+impl MagicDetector for ExtensionMagicDetector {
+    fn detect(&self, _bytes: &[u8], path: &str) -> String {
+        let path = Path::new(path);
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let magic = match extension.as_deref() {
+            Some("rb" | "gemspec" | "rake" | "ru") => "application/x-ruby",
+            Some("md" | "markdown") => "text/markdown",
+            Some("txt" | "rdoc") => "text/plain",
+            Some("html" | "htm") => "text/html",
+            Some("css") => "text/css",
+            Some("js") => "application/javascript",
+            Some("json") => "application/json",
+            Some("yml" | "yaml") => "application/x-yaml",
+            Some("xml") => "application/xml",
+            Some("c") => "text/x-csrc",
+            Some("h") => "text/x-chdr",
+            Some("cc" | "cpp") => "text/x-c++src",
+            Some("java") => "text/x-java",
+            Some("jar") => "application/java-archive",
+            Some("so") => "application/x-sharedlib",
+            Some("bundle" | "dylib") => "application/x-mach-binary",
+            Some("dll") => "application/vnd.microsoft.portable-executable",
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("svg") => "image/svg+xml",
+            Some("gz") => "application/gzip",
+            Some("sh") => "application/x-shellscript",
+            None if path.file_name().is_some_and(|name| {
+                matches!(
+                    name.to_str(),
+                    Some("Rakefile" | "Gemfile" | "LICENSE" | "README" | "CHANGELOG")
+                )
+            }) =>
+            {
+                "text/plain"
+            }
+            _ => "application/octet-stream",
+        };
+        magic.to_string()
+    }
+}
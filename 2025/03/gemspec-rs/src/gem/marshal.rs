@@ -0,0 +1,735 @@
+//! Reads and writes specifications in Ruby's Marshal 4.8 format, as
+//! RubyGems servers serve them from `quick/Marshal.4.8/*.gemspec.rz`.
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use serde::{
+    Deserialize,
+    de::{IntoDeserializer, value::StringDeserializer},
+};
+
+use super::{
+    Dependency, DependencyType, Error, Platform, Requirement, RequirementOperator, Specification,
+    Version, parse_platform,
+};
+
+/// Dumps `spec` the way `Marshal.dump` does for a `Gem::Specification`:
+/// the array built by `Gem::Specification#_dump`, nested as the spec's
+/// user-defined payload. Only the fields `_dump` records are included, so
+/// file lists and the like are dropped, as `Gem::Indexer` does.
+///
+/// Objects are never linked, so a spec that Ruby would dump with shared
+/// references comes out longer, but loads the same.
+pub fn dump_quick_spec(spec: &Specification) -> Result<Vec<u8>, Error> {
+    let mut fields = MarshalWriter::new();
+    fields.array(19);
+    fields.string(&spec.rubygems_version);
+    fields.integer(spec.specification_version.into());
+    fields.string(&spec.name);
+    fields.version(&spec.version);
+    fields.time(&spec.date);
+    fields.string(&spec.summary);
+    fields.requirement(spec.required_ruby_version.as_ref());
+    fields.requirement(spec.required_rubygems_version.as_ref());
+    fields.string(
+        spec.original_platform
+            .as_deref()
+            .unwrap_or(spec.platform.as_str()),
+    );
+    fields.array(spec.dependencies.len());
+    for dependency in &spec.dependencies {
+        fields.dependency(dependency);
+    }
+    // `rubyforge_project`, which `_dump` always writes as empty.
+    fields.string("");
+    fields.strings(&spec.email);
+    fields.strings(&spec.authors);
+    match &spec.description {
+        Some(description) => fields.string(description),
+        None => fields.nil(),
+    }
+    fields.string(&spec.homepage);
+    // `has_rdoc`, which `_dump` always writes as true.
+    fields.bool(true);
+    fields.platform(&spec.platform)?;
+    fields.strings(&spec.licenses);
+    let mut metadata: Vec<_> = spec.metadata.iter().collect();
+    metadata.sort();
+    fields.hash(metadata.len());
+    for (key, value) in metadata {
+        fields.string(key);
+        fields.string(value);
+    }
+
+    let mut writer = MarshalWriter::new();
+    writer.user_defined("Gem::Specification", &fields.bytes);
+    Ok(writer.bytes)
+}
+
+/// Deflates `bytes` with zlib, as `Gem.deflate` does for `.rz` files.
+pub fn compress_rz(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+/// Loads a `.gemspec.rz` quick spec, the inverse of `compress_rz` over
+/// `dump_quick_spec`, following `Gem::Specification._load`. Fields `_dump`
+/// doesn't record are left at their defaults, and the trailing fields older
+/// RubyGems didn't write, like `licenses`, are optional.
+pub fn load_quick_spec(bytes: &[u8]) -> Result<Specification, Error> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(bytes).read_to_end(&mut inflated)?;
+    read_quick_spec(&inflated).map_err(|e| Error::Marshal(format!("{e:#}")))
+}
+
+fn read_quick_spec(bytes: &[u8]) -> anyhow::Result<Specification> {
+    let fields = match MarshalReader::load(bytes)? {
+        Value::UserDefined(class, fields, _) if class == "Gem::Specification" => fields,
+        value => bail!("Expected a Gem::Specification, got {value:?}"),
+    };
+    let Value::Array(fields) = MarshalReader::load(&fields)? else {
+        bail!("Expected Gem::Specification data to be an array");
+    };
+    if fields.len() < 17 {
+        bail!(
+            "Expected at least 17 specification fields, got {}",
+            fields.len()
+        );
+    }
+    let mut fields = fields.into_iter();
+    let mut next = || fields.next().unwrap_or(Value::Nil);
+
+    let rubygems_version = next().into_string().context("rubygems_version")?;
+    let specification_version = match next() {
+        Value::Integer(version) => u8::try_from(version).context("specification_version")?,
+        value => bail!("Expected specification_version to be an integer, got {value:?}"),
+    };
+    let name = next().into_string().context("name")?;
+    let version = load_version(next()).context("version")?;
+    let date = load_time(next()).context("date")?;
+    let summary = next().into_string().context("summary")?;
+    let required_ruby_version = load_requirement(next()).context("required_ruby_version")?;
+    let required_rubygems_version =
+        load_requirement(next()).context("required_rubygems_version")?;
+    let original_platform = load_platform(next()).context("original_platform")?;
+    let dependencies = load_array(next())
+        .and_then(|dependencies| dependencies.into_iter().map(load_dependency).collect())
+        .context("dependencies")?;
+    let rubyforge_project = load_optional_string(next()).context("rubyforge_project")?;
+    let email = load_strings(next()).context("email")?;
+    let authors = load_strings(next()).context("authors")?;
+    let description = load_optional_string(next()).context("description")?;
+    let homepage = load_optional_string(next()).context("homepage")?;
+    // `has_rdoc`, which RubyGems no longer reads.
+    next();
+    let platform = load_platform(next()).context("platform")?;
+    let licenses = load_strings(next()).context("licenses")?;
+    let metadata = match next() {
+        Value::Nil => HashMap::new(),
+        Value::Hash(pairs) => pairs
+            .into_iter()
+            .map(|(key, value)| Ok((key.into_string()?, value.into_string()?)))
+            .collect::<anyhow::Result<_>>()
+            .context("metadata")?,
+        value => bail!("Expected metadata to be a hash, got {value:?}"),
+    };
+
+    Ok(Specification {
+        name,
+        version,
+        dependencies,
+        required_ruby_version: Some(required_ruby_version),
+        required_rubygems_version: Some(required_rubygems_version),
+        rubygems_version,
+        specification_version,
+        summary,
+        homepage: homepage.unwrap_or_default(),
+        licenses,
+        metadata,
+        original_platform: (original_platform != platform).then_some(original_platform),
+        platform: Platform::new(platform),
+        authors,
+        description,
+        email,
+        date,
+        rubyforge_project: rubyforge_project.filter(|project| !project.is_empty()),
+        ..Default::default()
+    })
+}
+
+fn load_array(value: Value) -> anyhow::Result<Vec<Value>> {
+    match value {
+        Value::Array(values) => Ok(values),
+        value => bail!("Expected an array, got {value:?}"),
+    }
+}
+
+fn load_optional_string(value: Value) -> anyhow::Result<Option<String>> {
+    match value {
+        Value::Nil => Ok(None),
+        value => value.into_string().map(Some),
+    }
+}
+
+/// A list of strings, which `email` and `authors` can also hold as a single
+/// string, and older specs leave as `nil`.
+fn load_strings(value: Value) -> anyhow::Result<Vec<String>> {
+    match value {
+        Value::Nil => Ok(Vec::new()),
+        Value::Array(values) => values.into_iter().map(Value::into_string).collect(),
+        value => Ok(vec![value.into_string()?]),
+    }
+}
+
+fn load_version(value: Value) -> anyhow::Result<Version> {
+    let Value::UserMarshal(class, data) = value else {
+        bail!("Expected a Gem::Version, got {value:?}");
+    };
+    if class != "Gem::Version" {
+        bail!("Expected a Gem::Version, got a {class}");
+    }
+    let version = load_array(*data)?
+        .into_iter()
+        .next()
+        .context("empty Gem::Version")?
+        .into_string()?;
+    Ok(Version::from_str(&version)?)
+}
+
+fn load_requirement(value: Value) -> anyhow::Result<Requirement> {
+    let Value::UserMarshal(class, data) = value else {
+        bail!("Expected a Gem::Requirement, got {value:?}");
+    };
+    if class != "Gem::Requirement" {
+        bail!("Expected a Gem::Requirement, got a {class}");
+    }
+    let requirements = load_array(*data)?
+        .into_iter()
+        .next()
+        .context("empty Gem::Requirement")?;
+    let requirements = load_array(requirements)?
+        .into_iter()
+        .map(|clause| {
+            let [op, version] = <[Value; 2]>::try_from(load_array(clause)?)
+                .map_err(|clause| anyhow::anyhow!("Expected [op, version], got {clause:?}"))?;
+            let op: StringDeserializer<serde::de::value::Error> =
+                op.into_string()?.into_deserializer();
+            let op = RequirementOperator::deserialize(op)?;
+            Ok((op, load_version(version)?))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Requirement::new(requirements))
+}
+
+fn load_dependency(value: Value) -> anyhow::Result<Dependency> {
+    let Value::Object(class, ivars) = value else {
+        bail!("Expected a Gem::Dependency, got {value:?}");
+    };
+    if class != "Gem::Dependency" {
+        bail!("Expected a Gem::Dependency, got a {class}");
+    }
+    let mut ivars: HashMap<_, _> = ivars.into_iter().collect();
+    let name = ivars
+        .remove("@name")
+        .context("Gem::Dependency without @name")?
+        .into_string()?;
+    // Dependencies dumped before `@requirement` existed only have
+    // `@version_requirements`.
+    let requirement = ivars
+        .remove("@requirement")
+        .or_else(|| ivars.remove("@version_requirements"))
+        .with_context(|| format!("dependency {name} has no requirement"))?;
+    let requirement =
+        load_requirement(requirement).with_context(|| format!("dependency {name}"))?;
+    let r#type = match ivars.remove("@type") {
+        None | Some(Value::Nil) => DependencyType::Runtime,
+        Some(Value::Symbol(r#type)) if r#type == "runtime" => DependencyType::Runtime,
+        Some(Value::Symbol(r#type)) if r#type == "development" => DependencyType::Development,
+        Some(value) => bail!("dependency {name} has unknown type {value:?}"),
+    };
+    Ok(Dependency::new(name, requirement, r#type))
+}
+
+/// Either a platform string or a `Gem::Platform`, which prints as its
+/// parts joined by dashes.
+fn load_platform(value: Value) -> anyhow::Result<String> {
+    match value {
+        Value::Nil => Ok("ruby".to_string()),
+        Value::Object(class, ivars) if class == "Gem::Platform" => {
+            let mut ivars: HashMap<_, _> = ivars.into_iter().collect();
+            let parts = ["@cpu", "@os", "@version"]
+                .into_iter()
+                .filter_map(|ivar| load_optional_string(ivars.remove(ivar)?).transpose())
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(parts.join("-"))
+        }
+        value => value.into_string(),
+    }
+}
+
+/// The inverse of `MarshalWriter::time`. The zone is ignored, since the
+/// packed fields are always UTC.
+fn load_time(value: Value) -> anyhow::Result<DateTime<Utc>> {
+    let packed = match value {
+        Value::UserDefined(class, packed, _) if class == "Time" => packed,
+        value => bail!("Expected a Time, got {value:?}"),
+    };
+    let packed = <[u8; 8]>::try_from(packed.as_slice())
+        .map_err(|_| anyhow::anyhow!("Expected 8 bytes of Time, got {}", packed.len()))?;
+    let high = u32::from_le_bytes(packed[..4].try_into().unwrap());
+    let low = u32::from_le_bytes(packed[4..].try_into().unwrap());
+    // Times from before the packed format are seconds and microseconds.
+    if high & 1 << 31 == 0 {
+        return DateTime::from_timestamp(high.into(), low % 1_000_000 * 1000)
+            .with_context(|| format!("Invalid Time {high}.{low}"));
+    }
+    NaiveDate::from_ymd_opt(
+        ((high >> 14) & 0xffff) as i32 + 1900,
+        ((high >> 10) & 0xf) + 1,
+        (high >> 5) & 0x1f,
+    )
+    .and_then(|date| {
+        date.and_hms_micro_opt(
+            high & 0x1f,
+            (low >> 26) & 0x3f,
+            (low >> 20) & 0x3f,
+            low & 0xfffff,
+        )
+    })
+    .map(|time| time.and_utc())
+    .with_context(|| format!("Invalid Time {packed:?}"))
+}
+
+/// The subset of Ruby's Marshal format that the spec indexes and quick
+/// specs use.
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // some payloads are only read for error messages
+pub(super) enum Value {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    String(Vec<u8>),
+    Symbol(String),
+    Array(Vec<Value>),
+    Hash(Vec<(Value, Value)>),
+    /// A plain object's class and instance variables, `@` included.
+    Object(String, Vec<(String, Value)>),
+    /// An object dumped with `marshal_dump`, and the value it returned.
+    UserMarshal(String, Box<Value>),
+    /// An object dumped with `_dump`, and the bytes it returned, along with
+    /// any instance variables, like `Time`'s `zone`.
+    UserDefined(String, Vec<u8>, Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(super) fn into_string(self) -> anyhow::Result<String> {
+        match self {
+            Value::String(bytes) => Ok(String::from_utf8(bytes)?),
+            value => bail!("Expected a string, got {value:?}"),
+        }
+    }
+}
+
+pub(super) struct MarshalReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    symbols: Vec<String>,
+    objects: Vec<Value>,
+}
+
+impl<'a> MarshalReader<'a> {
+    /// Reads the single value in a Marshal 4.8 dump.
+    pub(super) fn load(bytes: &'a [u8]) -> anyhow::Result<Value> {
+        let mut reader = MarshalReader {
+            bytes,
+            pos: 0,
+            symbols: Vec::new(),
+            objects: Vec::new(),
+        };
+        if reader.take(2)? != [4, 8] {
+            bail!("Expected Marshal 4.8 header");
+        }
+        reader.value()
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .with_context(|| format!("Unexpected end of Marshal data at {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn long(&mut self) -> anyhow::Result<i64> {
+        let c = self.byte()? as i8;
+        Ok(match c {
+            0 => 0,
+            5..=127 => (c - 5) as i64,
+            -128..=-5 => (c + 5) as i64,
+            1..=4 => {
+                let mut x = 0i64;
+                for (i, byte) in self.take(c as usize)?.iter().enumerate() {
+                    x |= (*byte as i64) << (8 * i);
+                }
+                x
+            }
+            -4..=-1 => {
+                let mut x = -1i64;
+                for (i, byte) in self.take(-c as usize)?.iter().enumerate() {
+                    x &= !(0xff << (8 * i));
+                    x |= (*byte as i64) << (8 * i);
+                }
+                x
+            }
+        })
+    }
+
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let len = self.long()?;
+        usize::try_from(len).with_context(|| format!("Negative length {len}"))
+    }
+
+    fn symbol(&mut self) -> anyhow::Result<String> {
+        match self.byte()? {
+            b':' => {
+                let len = self.len()?;
+                let symbol = String::from_utf8(self.take(len)?.to_vec())?;
+                self.symbols.push(symbol.clone());
+                Ok(symbol)
+            }
+            b';' => {
+                let idx = self.len()?;
+                self.symbols
+                    .get(idx)
+                    .cloned()
+                    .with_context(|| format!("Unknown symbol link {idx}"))
+            }
+            b'I' => {
+                let symbol = self.symbol()?;
+                self.ivars()?;
+                Ok(symbol)
+            }
+            c => bail!("Expected a symbol, got {:?}", c as char),
+        }
+    }
+
+    fn ivars(&mut self) -> anyhow::Result<Vec<(String, Value)>> {
+        (0..self.len()?)
+            .map(|_| Ok((self.symbol()?, self.value()?)))
+            .collect()
+    }
+
+    /// Reserves a slot in the object table, since Ruby numbers objects in
+    /// the order they start, not the order they finish.
+    fn entry(&mut self) -> usize {
+        self.objects.push(Value::Nil);
+        self.objects.len() - 1
+    }
+
+    fn value(&mut self) -> anyhow::Result<Value> {
+        match self.byte()? {
+            b'0' => Ok(Value::Nil),
+            b'T' => Ok(Value::Bool(true)),
+            b'F' => Ok(Value::Bool(false)),
+            b'i' => Ok(Value::Integer(self.long()?)),
+            b':' | b';' => {
+                self.pos -= 1;
+                Ok(Value::Symbol(self.symbol()?))
+            }
+            b'"' => {
+                let idx = self.entry();
+                let len = self.len()?;
+                let value = Value::String(self.take(len)?.to_vec());
+                self.objects[idx] = value.clone();
+                Ok(value)
+            }
+            // Only `_dump`ed objects keep their instance variables; for
+            // strings they're just the encoding.
+            b'I' => match self.value()? {
+                Value::UserDefined(class, bytes, _) => {
+                    let idx = self.objects.len() - 1;
+                    let value = Value::UserDefined(class, bytes, self.ivars()?);
+                    self.objects[idx] = value.clone();
+                    Ok(value)
+                }
+                value => {
+                    self.ivars()?;
+                    Ok(value)
+                }
+            },
+            b'[' => {
+                let idx = self.entry();
+                let len = self.len()?;
+                let values = (0..len)
+                    .map(|_| self.value())
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let value = Value::Array(values);
+                self.objects[idx] = value.clone();
+                Ok(value)
+            }
+            b'{' => {
+                let idx = self.entry();
+                let len = self.len()?;
+                let pairs = (0..len)
+                    .map(|_| Ok((self.value()?, self.value()?)))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let value = Value::Hash(pairs);
+                self.objects[idx] = value.clone();
+                Ok(value)
+            }
+            b'o' => {
+                let class = self.symbol()?;
+                let idx = self.entry();
+                let ivars = self.ivars()?;
+                let value = Value::Object(class, ivars);
+                self.objects[idx] = value.clone();
+                Ok(value)
+            }
+            b'u' => {
+                let class = self.symbol()?;
+                let len = self.len()?;
+                let value = Value::UserDefined(class, self.take(len)?.to_vec(), Vec::new());
+                self.objects.push(value.clone());
+                Ok(value)
+            }
+            b'U' => {
+                let class = self.symbol()?;
+                let idx = self.entry();
+                let data = self.value()?;
+                let value = Value::UserMarshal(class, Box::new(data));
+                self.objects[idx] = value.clone();
+                Ok(value)
+            }
+            b'@' => {
+                let idx = self.len()?;
+                self.objects
+                    .get(idx)
+                    .cloned()
+                    .with_context(|| format!("Unknown object link {idx}"))
+            }
+            c => bail!(
+                "Unsupported Marshal type {:?} at {}",
+                c as char,
+                self.pos - 1
+            ),
+        }
+    }
+}
+
+struct MarshalWriter {
+    bytes: Vec<u8>,
+    symbols: Vec<&'static str>,
+}
+
+impl MarshalWriter {
+    fn new() -> Self {
+        MarshalWriter {
+            bytes: vec![4, 8],
+            symbols: Vec::new(),
+        }
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    /// The inverse of `MarshalReader::long`.
+    fn long(&mut self, x: i64) {
+        match x {
+            0 => self.byte(0),
+            1..=122 => self.byte(x as u8 + 5),
+            -123..=-1 => self.byte((x - 5) as u8),
+            _ => {
+                let mut buf = Vec::new();
+                let mut rest = x;
+                let len = loop {
+                    buf.push(rest as u8);
+                    rest >>= 8;
+                    if rest == 0 {
+                        break buf.len() as i8;
+                    }
+                    if rest == -1 {
+                        break -(buf.len() as i8);
+                    }
+                };
+                self.byte(len as u8);
+                self.bytes.extend(buf);
+            }
+        }
+    }
+
+    fn raw(&mut self, bytes: &[u8]) {
+        self.long(bytes.len() as i64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn symbol(&mut self, symbol: &'static str) {
+        match self.symbols.iter().position(|s| *s == symbol) {
+            Some(idx) => {
+                self.byte(b';');
+                self.long(idx as i64);
+            }
+            None => {
+                self.byte(b':');
+                self.raw(symbol.as_bytes());
+                self.symbols.push(symbol);
+            }
+        }
+    }
+
+    fn nil(&mut self) {
+        self.byte(b'0');
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.byte(if value { b'T' } else { b'F' });
+    }
+
+    fn integer(&mut self, value: i64) {
+        self.byte(b'i');
+        self.long(value);
+    }
+
+    /// A UTF-8 string, tagged with its encoding like Ruby's own strings.
+    fn string(&mut self, value: &str) {
+        self.byte(b'I');
+        self.byte(b'"');
+        self.raw(value.as_bytes());
+        self.long(1);
+        self.symbol("E");
+        self.bool(true);
+    }
+
+    fn strings(&mut self, values: &[String]) {
+        self.array(values.len());
+        for value in values {
+            self.string(value);
+        }
+    }
+
+    fn array(&mut self, len: usize) {
+        self.byte(b'[');
+        self.long(len as i64);
+    }
+
+    fn hash(&mut self, len: usize) {
+        self.byte(b'{');
+        self.long(len as i64);
+    }
+
+    /// An object whose class defines `marshal_dump`; the caller writes the
+    /// dumped value next.
+    fn user_marshal(&mut self, class: &'static str) {
+        self.byte(b'U');
+        self.symbol(class);
+    }
+
+    /// An object whose class defines `_dump`, with its dumped bytes.
+    fn user_defined(&mut self, class: &'static str, bytes: &[u8]) {
+        self.byte(b'u');
+        self.symbol(class);
+        self.raw(bytes);
+    }
+
+    /// A plain object; the caller writes `ivars` name and value pairs next.
+    fn object(&mut self, class: &'static str, ivars: usize) {
+        self.byte(b'o');
+        self.symbol(class);
+        self.long(ivars as i64);
+    }
+
+    fn version(&mut self, version: &Version) {
+        self.user_marshal("Gem::Version");
+        self.array(1);
+        self.string(version.as_str());
+    }
+
+    /// Writes `Gem::Requirement.default` for a missing requirement.
+    fn requirement(&mut self, requirement: Option<&Requirement>) {
+        let Some(requirement) = requirement else {
+            return self.requirement(Some(&Requirement::default()));
+        };
+        self.user_marshal("Gem::Requirement");
+        self.array(1);
+        self.array(requirement.requirements.len());
+        for (op, version) in &requirement.requirements {
+            self.array(2);
+            self.string(&op.to_string());
+            self.version(version);
+        }
+    }
+
+    fn dependency(&mut self, dependency: &Dependency) {
+        self.object("Gem::Dependency", 5);
+        self.symbol("@name");
+        self.string(&dependency.name);
+        self.symbol("@requirement");
+        self.requirement(Some(&dependency.requirement));
+        self.symbol("@type");
+        self.symbol(match dependency.r#type {
+            DependencyType::Runtime => "runtime",
+            DependencyType::Development => "development",
+        });
+        self.symbol("@prerelease");
+        self.bool(false);
+        // Ruby links this to `@requirement`; a copy loads the same.
+        self.symbol("@version_requirements");
+        self.requirement(Some(&dependency.requirement));
+    }
+
+    /// `ruby` is dumped as a string, anything else as a `Gem::Platform`.
+    fn platform(&mut self, platform: &Platform) -> Result<(), Error> {
+        if platform.as_str() == "ruby" {
+            self.string("ruby");
+            return Ok(());
+        }
+        let (cpu, os, version) =
+            parse_platform(platform.as_str()).map_err(|e| Error::PlatformParse(e.to_string()))?;
+        self.object("Gem::Platform", 3);
+        for (ivar, value) in [("@cpu", cpu), ("@os", Some(os)), ("@version", version)] {
+            self.symbol(ivar);
+            match value {
+                Some(value) => self.string(value),
+                None => self.nil(),
+            }
+        }
+        Ok(())
+    }
+
+    /// A UTC `Time`, in the packed format `Time#_dump` uses, to microsecond
+    /// precision.
+    fn time(&mut self, time: &DateTime<Utc>) {
+        let year = (time.year() - 1900) as u32 & 0xffff;
+        let high =
+            1 << 31 | 1 << 30 | year << 14 | time.month0() << 10 | time.day() << 5 | time.hour();
+        let low =
+            time.minute() << 26 | time.second() << 20 | (time.nanosecond() / 1000 % 1_000_000);
+        let mut packed = high.to_le_bytes().to_vec();
+        packed.extend(low.to_le_bytes());
+
+        self.byte(b'I');
+        self.user_defined("Time", &packed);
+        self.long(1);
+        self.symbol("zone");
+        // "UTC" is US-ASCII, which Marshal records as `E` false.
+        self.byte(b'I');
+        self.byte(b'"');
+        self.raw(b"UTC");
+        self.long(1);
+        self.symbol("E");
+        self.bool(false);
+    }
+}
@@ -3,24 +3,45 @@ use std::io::Read;
 use sha2::Digest;
 
 pub mod gem {
-    use std::io::BufReader;
+    #[cfg(feature = "proptest")]
+    pub mod arbitrary;
+    pub mod builder;
+    mod error;
+    pub mod index;
+    pub mod magic;
+    pub mod marshal;
+    mod spdx;
+
+    pub use error::Error;
+    pub use globset::GlobSet;
+    pub use url::Url;
+
+    use std::io::{BufRead, BufReader};
     use std::{
-        collections::HashMap,
+        collections::{BTreeMap, BTreeSet, HashMap},
         fmt::Display,
-        io::{Read, Seek},
+        fs::File,
+        io::{Cursor, Read, Seek},
         marker::PhantomData,
+        path::{Path, PathBuf},
         str::FromStr,
     };
 
     use anyhow::{Context, bail};
     use chrono::DateTime;
     use flate2::bufread::GzDecoder;
-    use saphyr::LoadableYamlNode;
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    use rsa::{Pkcs1v15Sign, RsaPublicKey, pkcs8::DecodePublicKey};
     use serde::{Deserialize, Deserializer, Serialize, de::Visitor};
     use serde_with::serde_as;
+    use sha2::Digest;
     use sha2::digest::generic_array::GenericArray;
-    use strum_macros::EnumString;
+    use strum_macros::{Display as StrumDisplay, EnumIter, EnumString};
     use tar::{Archive, Entry};
+    use x509_cert::{
+        Certificate,
+        der::{DecodePem, Encode},
+    };
 
     fn deserialize_vec<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
     where
@@ -78,7 +99,44 @@ pub mod gem {
         deserializer.deserialize_any(v)
     }
 
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+    /// Returns the field names a derived `Deserialize` impl expects.
+    fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+        struct FieldsDeserializer<'a> {
+            fields: &'a mut &'static [&'static str],
+        }
+
+        impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+            type Error = serde::de::value::Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+                Err(serde::de::Error::custom("not a struct"))
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Self::Error> {
+                *self.fields = fields;
+                Err(serde::de::Error::custom("captured fields"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(FieldsDeserializer {
+            fields: &mut fields,
+        });
+        fields
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
     #[serde(deny_unknown_fields)]
     pub struct Specification {
         pub name: String,
@@ -92,15 +150,19 @@ pub mod gem {
         pub summary: String,
         pub require_paths: Vec<String>,
         pub homepage: String,
+        #[serde(deserialize_with = "deserialize_vec")]
         pub licenses: Vec<String>,
         #[serde(default)]
         pub metadata: HashMap<String, String>,
         pub files: Vec<String>,
+        #[serde(default)]
         pub platform: Platform,
+        #[serde(deserialize_with = "deserialize_vec")]
         pub authors: Vec<String>,
         pub autorequire: Option<String>,
         pub description: Option<String>,
         pub bindir: Option<String>,
+        #[serde(deserialize_with = "deserialize_vec")]
         pub executables: Vec<String>,
         #[serde(deserialize_with = "deserialize_vec", default)]
         pub email: Vec<String>,
@@ -116,253 +178,3015 @@ pub mod gem {
         pub default_executable: Option<String>,
         pub has_rdoc: Option<bool>,
         pub original_platform: Option<String>,
+        #[serde(skip)]
+        extra_fields: HashMap<String, serde_yaml::Value>,
     }
 
     impl Specification {
-        pub fn full_name(&self) -> String {
-            format!("{}-{}-{}", self.name, self.version.version, self.platform.0)
+        /// Parses the first YAML document, rejecting any fields this crate
+        /// doesn't model.
+        pub fn from_yaml(yaml: &str) -> Result<Specification, Error> {
+            // Psych only loads the first document, and serde_yaml resolves
+            // the `&id001`/`*id001` aliases older RubyGems emitted.
+            let document = serde_yaml::Deserializer::from_str(yaml)
+                .next()
+                .ok_or_else(|| Error::Specification("no YAML documents".to_string()))?;
+            Ok(Specification::deserialize(document)?)
         }
-    }
 
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-    pub struct Platform(String);
+        /// Like `from_yaml`, but collects fields this crate doesn't model
+        /// into `extra_fields` instead of failing.
+        pub fn from_yaml_lenient(yaml: &str) -> Result<Specification, Error> {
+            let document = serde_yaml::Deserializer::from_str(yaml)
+                .next()
+                .ok_or_else(|| Error::Specification("no YAML documents".to_string()))?;
+            let mapping = match serde_yaml::Value::deserialize(document)? {
+                serde_yaml::Value::Tagged(tagged) => tagged.value,
+                value => value,
+            };
+            let serde_yaml::Value::Mapping(mapping) = mapping else {
+                return Err(Error::Specification(
+                    "expected a Gem::Specification mapping".to_string(),
+                ));
+            };
 
-    impl Platform {
-        pub fn new<T: AsRef<str>>(platform: T) -> Self {
-            Platform(platform.as_ref().to_string())
+            let fields = struct_fields::<Specification>();
+            let mut known = serde_yaml::Mapping::new();
+            let mut extra_fields = HashMap::new();
+            for (key, value) in mapping {
+                match key.as_str() {
+                    Some(name) if fields.contains(&name) => {
+                        known.insert(key, value);
+                    }
+                    Some(name) => {
+                        extra_fields.insert(name.to_string(), value);
+                    }
+                    None => {
+                        return Err(Error::Specification(format!(
+                            "expected a string key, got {key:?}"
+                        )));
+                    }
+                }
+            }
+
+            let mut specification = Specification::deserialize(serde_yaml::Value::Mapping(known))?;
+            specification.extra_fields = extra_fields;
+            Ok(specification)
         }
-        pub fn as_str(&self) -> &str {
-            &self.0
+
+        /// Fields that `from_yaml_lenient` didn't recognize.
+        pub fn extra_fields(&self) -> &HashMap<String, serde_yaml::Value> {
+            &self.extra_fields
         }
-    }
 
-    impl Default for Platform {
-        fn default() -> Self {
-            Platform("ruby".to_string())
+        /// Whether the gem is built for a specific platform, rather than
+        /// being a pure-ruby gem.
+        pub fn is_platform_gem(&self) -> bool {
+            self.platform.as_str() != "ruby"
         }
-    }
 
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-    enum VersionSegment {
-        Number(u64),
-        String(String),
-    }
-    #[derive(Debug, PartialEq, Eq, Serialize, Default)]
-    pub struct Version {
-        version: String,
-        #[serde(skip)]
-        segments: Vec<VersionSegment>,
-    }
+        /// Whether the version is a prerelease, such as `1.0.0.rc1`.
+        pub fn is_prerelease(&self) -> bool {
+            self.version.is_prerelease()
+        }
 
-    impl Version {
-        pub fn as_str(&self) -> &str {
-            &self.version
+        /// Matches `Gem::Specification#full_name`, which omits the platform
+        /// for pure-ruby gems.
+        pub fn full_name(&self) -> String {
+            if self.is_platform_gem() {
+                format!("{}-{}-{}", self.name, self.version.version, self.platform.0)
+            } else {
+                format!("{}-{}", self.name, self.version.version)
+            }
         }
-    }
 
-    impl<'de> Deserialize<'de> for Version {
-        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            #[derive(Debug, Deserialize)]
-            struct V {
-                version: String,
+        /// `<full_name>.gem`, as found in `gems/` and `cache/`.
+        pub fn gem_file_name(&self) -> String {
+            format!("{}.gem", self.full_name())
+        }
+
+        /// `<full_name>.gemspec`, as found in `specifications/`.
+        pub fn spec_file_name(&self) -> String {
+            format!("{}.gemspec", self.full_name())
+        }
+
+        /// The path of the marshalled spec in a gem server's index.
+        pub fn quick_spec_path(&self) -> String {
+            format!("quick/Marshal.4.8/{}.gemspec.rz", self.full_name())
+        }
+
+        /// Parses `homepage` as an `http` or `https` URL, dropping a trailing
+        /// slash from the path so that `https://example.com/gem/` and
+        /// `https://example.com/gem` are the same. `None` when it's blank or
+        /// invalid.
+        pub fn homepage_url(&self) -> Option<Url> {
+            let mut url = Url::parse(self.homepage.trim()).ok()?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return None;
             }
-            let version = V::deserialize(deserializer)?;
-            Version::from_str(&version.version).map_err(serde::de::Error::custom)
+            if url.path().len() > 1 && url.path().ends_with('/') {
+                let path = url.path().trim_end_matches('/').to_string();
+                url.set_path(&path);
+            }
+            Some(url)
         }
-    }
 
-    impl FromStr for Version {
-        type Err = anyhow::Error;
+        /// Returns a warning for each problem that `gem build` would let
+        /// through but that users would trip over.
+        pub fn validate(&self) -> Vec<String> {
+            let mut warnings = Vec::new();
+            if let Some(url) = self.homepage_url()
+                && url.scheme() != "https"
+            {
+                warnings.push(format!("homepage {url} is not HTTPS"));
+            }
+            warnings
+        }
 
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let segments: Vec<VersionSegment> = s
-                .split('.')
-                .map(|segment| {
-                    if segment.is_empty() {
-                        bail!("Empty segment in version string {:?}", s);
-                    }
-                    Ok(if let Ok(number) = segment.parse::<u64>() {
-                        VersionSegment::Number(number)
-                    } else {
-                        VersionSegment::String(segment.to_string())
-                    })
+        pub fn homepage_uri(&self) -> Option<&str> {
+            self.metadata.get("homepage_uri").map(String::as_str)
+        }
+
+        pub fn source_code_uri(&self) -> Option<&str> {
+            self.metadata.get("source_code_uri").map(String::as_str)
+        }
+
+        pub fn changelog_uri(&self) -> Option<&str> {
+            self.metadata.get("changelog_uri").map(String::as_str)
+        }
+
+        pub fn bug_tracker_uri(&self) -> Option<&str> {
+            self.metadata.get("bug_tracker_uri").map(String::as_str)
+        }
+
+        pub fn documentation_uri(&self) -> Option<&str> {
+            self.metadata.get("documentation_uri").map(String::as_str)
+        }
+
+        pub fn funding_uri(&self) -> Option<&str> {
+            self.metadata.get("funding_uri").map(String::as_str)
+        }
+
+        /// RubyGems only enforces MFA when the value is exactly `"true"`.
+        pub fn mfa_required(&self) -> bool {
+            self.metadata
+                .get("rubygems_mfa_required")
+                .is_some_and(|value| value == "true")
+        }
+
+        /// The dependencies that apply when installing on `platform`: every
+        /// dependency without a platform, plus those whose platform matches.
+        /// Dependencies whose requirement nothing can satisfy, such as
+        /// `> 2, < 1`, are left out.
+        pub fn dependencies_for(&self, platform: &Platform) -> Vec<&Dependency> {
+            self.dependencies
+                .iter()
+                .filter(|dependency| {
+                    dependency
+                        .platform
+                        .as_ref()
+                        .is_none_or(|scoped| scoped.specificity(platform).is_some())
+                        && dependency.requirement.is_satisfiable()
                 })
-                .collect::<Result<Vec<_>, _>>()?;
-            Ok(Version {
-                version: s.to_string(),
-                segments,
-            })
+                .collect()
         }
-    }
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-    pub struct Dependency {
-        name: String,
-        requirement: Requirement,
-        r#type: DependencyType,
-    }
-    impl Dependency {
-        pub fn new(name: String, requirement: Requirement, r#type: DependencyType) -> Self {
-            Dependency {
-                name,
-                requirement,
-                r#type,
+
+        /// `required_ruby_version`, or `>= 0` when it's missing or has no
+        /// constraints, as RubyGems treats both.
+        pub fn effective_required_ruby_version(&self) -> Requirement {
+            self.required_ruby_version
+                .clone()
+                .filter(|requirement| !requirement.requirements.is_empty())
+                .unwrap_or_default()
+        }
+
+        /// Whether `required_ruby_version` allows the given Ruby.
+        pub fn supports_ruby(&self, ruby: &Version) -> Result<bool, Error> {
+            self.effective_required_ruby_version().satisfied_by(ruby)
+        }
+
+        /// Whether `required_rubygems_version` allows the given RubyGems.
+        pub fn supports_rubygems(&self, rubygems: &Version) -> Result<bool, Error> {
+            self.required_rubygems_version
+                .as_ref()
+                .map_or(Ok(true), |requirement| requirement.satisfied_by(rubygems))
+        }
+
+        /// Joins the licenses into an SPDX expression, e.g. `MIT OR
+        /// Apache-2.0`, since a gem may be used under any of them. SPDX
+        /// identifiers are case-insensitive, so `mit` is accepted and written
+        /// as `MIT`. Fails with every license that isn't an identifier.
+        pub fn license_expression(&self) -> Result<String, Vec<String>> {
+            let mut ids = Vec::new();
+            let mut unrecognized = Vec::new();
+            for license in &self.licenses {
+                let found = spdx::LICENSE_IDS.binary_search_by(|id| {
+                    id.bytes()
+                        .map(|b| b.to_ascii_lowercase())
+                        .cmp(license.bytes().map(|b| b.to_ascii_lowercase()))
+                });
+                match found {
+                    Ok(idx) => ids.push(spdx::LICENSE_IDS[idx]),
+                    Err(_) => unrecognized.push(license.clone()),
+                }
+            }
+            if !unrecognized.is_empty() {
+                return Err(unrecognized);
             }
+            Ok(ids.join(" OR "))
         }
-        pub fn name(&self) -> &str {
-            &self.name
+
+        /// Renders the dependencies as a Gemfile, with runtime dependencies
+        /// at the top level and development ones in a `:development` group.
+        /// Constraints are quoted one per argument, and `>= 0` is left out.
+        pub fn to_gemfile_lines(&self) -> Vec<String> {
+            let gem_line = |dependency: &Dependency| {
+                let mut line = format!("gem {:?}", dependency.name);
+                if !dependency.requirement.is_none() {
+                    for (op, version) in &dependency.requirement.requirements {
+                        line.push_str(&format!(", \"{op} {version}\""));
+                    }
+                }
+                line
+            };
+            let (runtime, development): (Vec<_>, Vec<_>) = self
+                .dependencies
+                .iter()
+                .partition(|dependency| dependency.r#type == DependencyType::Runtime);
+
+            let mut lines = vec!["source \"https://rubygems.org\"".to_string()];
+            if !runtime.is_empty() {
+                lines.push(String::new());
+                lines.extend(runtime.into_iter().map(gem_line));
+            }
+            if !development.is_empty() {
+                lines.push(String::new());
+                lines.push("group :development do".to_string());
+                lines.extend(
+                    development
+                        .into_iter()
+                        .map(|dependency| format!("  {}", gem_line(dependency))),
+                );
+                lines.push("end".to_string());
+            }
+            lines
         }
-        pub fn requirement(&self) -> &Requirement {
-            &self.requirement
+
+        /// Renders the fields of `/api/v1/gems/{name}.json` that come from the
+        /// specification: `authors` joined into one string, `info` from the
+        /// description or summary, the `*_uri` links from `metadata`, and
+        /// `dependencies` split into `runtime` and `development` lists.
+        /// Server-side fields like `downloads` and `sha` are left out.
+        pub fn to_api_json(&self) -> serde_json::Value {
+            let dependencies = |r#type: DependencyType| {
+                self.dependencies
+                    .iter()
+                    .filter(|dependency| dependency.r#type == r#type)
+                    .map(|dependency| {
+                        serde_json::json!({
+                            "name": dependency.name,
+                            "requirements": dependency.requirement.to_string(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let info = [self.description.as_deref(), Some(self.summary.as_str())]
+                .into_iter()
+                .flatten()
+                .find(|info| !info.is_empty());
+            let homepage = Some(self.homepage.as_str()).filter(|homepage| !homepage.is_empty());
+
+            let mut json = serde_json::json!({
+                "name": self.name,
+                "version": self.version,
+                "platform": self.platform,
+                "authors": self.authors.join(", "),
+                "info": info,
+                "licenses": self.licenses,
+                "metadata": self.metadata,
+                "homepage_uri": self.metadata.get("homepage_uri").map(String::as_str).or(homepage),
+                "dependencies": {
+                    "development": dependencies(DependencyType::Development),
+                    "runtime": dependencies(DependencyType::Runtime),
+                },
+            });
+            for key in [
+                "wiki_uri",
+                "documentation_uri",
+                "mailing_list_uri",
+                "source_code_uri",
+                "bug_tracker_uri",
+                "changelog_uri",
+                "funding_uri",
+            ] {
+                json[key] = self.metadata.get(key).map(String::as_str).into();
+            }
+            json
         }
-        pub fn r#type(&self) -> DependencyType {
-            self.r#type
+
+        /// Reports what changed going from this spec to `other`, such as
+        /// between two releases of a gem.
+        pub fn diff<'a>(&'a self, other: &'a Specification) -> SpecDiff<'a> {
+            let find = |spec: &'a Specification, name: &str| {
+                spec.dependencies.iter().find(|d| d.name == name)
+            };
+            let mut diff = SpecDiff::default();
+            for before in &self.dependencies {
+                match find(other, &before.name) {
+                    None => diff.removed_dependencies.push(before),
+                    Some(after) if after.requirement != before.requirement => {
+                        diff.changed_dependencies.insert(
+                            &before.name,
+                            Change {
+                                before: &before.requirement,
+                                after: &after.requirement,
+                            },
+                        );
+                    }
+                    Some(_) => {}
+                }
+            }
+            diff.added_dependencies = other
+                .dependencies
+                .iter()
+                .filter(|after| find(self, &after.name).is_none())
+                .collect();
+
+            let before_files: BTreeSet<&str> = self.files.iter().map(String::as_str).collect();
+            let after_files: BTreeSet<&str> = other.files.iter().map(String::as_str).collect();
+            diff.added_files = after_files.difference(&before_files).copied().collect();
+            diff.removed_files = before_files.difference(&after_files).copied().collect();
+
+            let sorted = |licenses: &'a [String]| {
+                let mut licenses: Vec<&str> = licenses.iter().map(String::as_str).collect();
+                licenses.sort_unstable();
+                licenses
+            };
+            if sorted(&self.licenses) != sorted(&other.licenses) {
+                diff.licenses = Some(Change {
+                    before: &self.licenses,
+                    after: &other.licenses,
+                });
+            }
+
+            // A missing requirement means the same as `>= 0`.
+            let required_ruby_version = |spec: &'a Specification| {
+                spec.required_ruby_version
+                    .as_ref()
+                    .filter(|requirement| !requirement.is_none())
+            };
+            let (before, after) = (required_ruby_version(self), required_ruby_version(other));
+            if before != after {
+                diff.required_ruby_version = Some(Change { before, after });
+            }
+            diff
         }
     }
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString)]
-    pub enum DependencyType {
-        #[serde(rename = ":runtime")]
-        Runtime,
-        #[serde(rename = ":development")]
-        Development,
-    }
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-    pub struct Requirement {
-        requirements: Vec<(RequirementOperator, Version)>,
+
+    /// The differences `Specification::diff` found. Dependencies are matched
+    /// by name, and files are listed in sorted order.
+    #[derive(Debug, Default, PartialEq, Eq, Serialize)]
+    pub struct SpecDiff<'a> {
+        pub added_dependencies: Vec<&'a Dependency>,
+        pub removed_dependencies: Vec<&'a Dependency>,
+        pub changed_dependencies: BTreeMap<&'a str, Change<&'a Requirement>>,
+        pub added_files: Vec<&'a str>,
+        pub removed_files: Vec<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub licenses: Option<Change<&'a [String]>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub required_ruby_version: Option<Change<Option<&'a Requirement>>>,
     }
 
-    impl Requirement {
-        pub fn new(requirements: Vec<(RequirementOperator, Version)>) -> Self {
-            Requirement { requirements }
-        }
-        pub fn requirements(&self) -> &[(RequirementOperator, Version)] {
-            &self.requirements
+    impl SpecDiff<'_> {
+        pub fn is_empty(&self) -> bool {
+            *self == SpecDiff::default()
         }
     }
 
-    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-    pub enum RequirementOperator {
-        #[serde(rename = "=")]
-        Equal,
-        #[serde(rename = ">")]
-        GreaterThan,
-        #[serde(rename = ">=")]
-        GreaterThanOrEqual,
-        #[serde(rename = "<")]
-        LessThan,
-        #[serde(rename = "<=")]
-        LessThanOrEqual,
-        #[serde(rename = "!=")]
-        NotEqual,
-        #[serde(rename = "~>")]
-        Tilde,
-        // #[serde(untagged)]
-        // Unknown(String),
-        Unknown,
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    pub struct Change<T> {
+        pub before: T,
+        pub after: T,
     }
 
-    // enum PackageEntry {
-    //     Metadata,
-    //     Checksums(HashMap<String, String>),
-    //     Signature(String, String),
-    // }
+    /// The fields that identify a gem, recovered from YAML that doesn't parse
+    /// as a whole `Specification`.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+    pub struct PartialSpecification {
+        pub name: Option<String>,
+        pub version: Option<String>,
+        pub platform: Option<String>,
+    }
 
-    #[derive()]
-    pub struct Package<R>
-    where
-        R: Read + Seek,
-    {
-        archive: Archive<R>,
+    impl PartialSpecification {
+        /// Reads YAML events until the stream ends or fails to parse, picking
+        /// out the top-level `name`, `platform` and `version`, which may be
+        /// a `Gem::Version` mapping or a plain scalar.
+        pub fn from_yaml(yaml: &str) -> PartialSpecification {
+            use saphyr_parser::Event;
+
+            /// A mapping or sequence being read, and the key whose value is
+            /// being read in it, if it's a mapping.
+            struct Frame {
+                mapping: bool,
+                key: Option<String>,
+            }
+
+            let mut partial = PartialSpecification::default();
+            let mut stack: Vec<Frame> = Vec::new();
+            for event in saphyr_parser::Parser::new_from_str(yaml) {
+                let Ok((event, _)) = event else {
+                    break;
+                };
+                let value = match event {
+                    Event::MappingStart(..) | Event::SequenceStart(..) => {
+                        stack.push(Frame {
+                            mapping: matches!(event, Event::MappingStart(..)),
+                            key: None,
+                        });
+                        continue;
+                    }
+                    Event::MappingEnd | Event::SequenceEnd => {
+                        stack.pop();
+                        None
+                    }
+                    Event::Scalar(value, ..) => Some(value),
+                    Event::Alias(_) => None,
+                    // Psych only loads the first document.
+                    Event::DocumentEnd => break,
+                    _ => continue,
+                };
+                let Some(frame) = stack.last_mut() else {
+                    continue;
+                };
+                if frame.mapping && frame.key.is_none() {
+                    // A key; collections can be keys too, but not ones we need.
+                    frame.key = Some(value.map(|key| key.into_owned()).unwrap_or_default());
+                    continue;
+                }
+                let keys: Vec<&str> = stack.iter().filter_map(|f| f.key.as_deref()).collect();
+                if let Some(value) = value {
+                    let value = Some(value.into_owned());
+                    match (stack.len(), keys.as_slice()) {
+                        (1, ["name"]) => partial.name = value,
+                        (1, ["platform"]) => partial.platform = value,
+                        (1, ["version"]) | (2, ["version", "version"]) => partial.version = value,
+                        _ => {}
+                    }
+                }
+                if let Some(frame) = stack.last_mut() {
+                    frame.key = None;
+                }
+            }
+            partial
+        }
     }
 
-    impl<R> Package<R>
-    where
-        R: Read + Seek,
-    {
-        pub fn new(io: R) -> Package<R> {
-            let archive = tar::Archive::new(io);
-            Package { archive }
+    /// Builds a `Specification` for producing gems, filling in the defaults
+    /// `Gem::Specification.new` would.
+    #[derive(Debug, Default)]
+    pub struct SpecificationBuilder {
+        spec: Specification,
+        date: Option<DateTime<chrono::Utc>>,
+    }
+
+    impl Specification {
+        pub fn builder() -> SpecificationBuilder {
+            SpecificationBuilder::default()
         }
+    }
 
-        pub fn specification(&mut self) -> anyhow::Result<Specification> {
-            let mut entries = self.archive.entries_with_seek()?;
-            let entry = entries
-                .find(|entry| {
-                    let entry = entry.as_ref().unwrap();
-                    entry.path().unwrap().to_str() == Some("metadata.gz")
-                })
-                .expect("metadata.gz")?;
+    impl SpecificationBuilder {
+        pub fn name(mut self, name: impl Into<String>) -> Self {
+            self.spec.name = name.into();
+            self
+        }
 
-            let mut reader = flate2::read::GzDecoder::new(entry);
+        pub fn version(mut self, version: Version) -> Self {
+            self.spec.version = version;
+            self
+        }
 
-            let mut contents: String = String::new();
-            reader.read_to_string(&mut contents)?;
+        pub fn summary(mut self, summary: impl Into<String>) -> Self {
+            self.spec.summary = summary.into();
+            self
+        }
 
-            let specification: Specification =
-                serde_yaml::from_str(&contents).inspect_err(|err| {
-                    saphyr::Yaml::load_from_str(&contents)
-                        .inspect_err(|err| {
-                            eprintln!("Failed to parse YAML: {:#?}", err);
-                        })
-                        .inspect(|yaml| {
-                            println!("YAML: {yaml:#?}");
-                        })
-                        .unwrap();
-                    // for (idx, line) in contents.lines().enumerate() {
-                    //     let idx = idx + 1;
-                    //     println!("{idx:>3}| {line}");
-                    // }
-                })?;
+        pub fn authors<I, S>(mut self, authors: I) -> Self
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<String>,
+        {
+            self.spec.authors = authors.into_iter().map(Into::into).collect();
+            self
+        }
 
-            self.archive.reset()?;
-            Ok(specification)
+        pub fn add_dependency(mut self, dependency: Dependency) -> Self {
+            self.spec.dependencies.push(dependency);
+            self
         }
 
-        pub fn each_entry(
-            &mut self,
-            mut f: impl FnMut(&mut Entry<GzDecoder<BufReader<Entry<R>>>>) -> anyhow::Result<()>,
-        ) -> anyhow::Result<()> {
-            let mut entries = self.archive.entries_with_seek()?;
-
-            let entry = entries
-                .find(|entry| {
-                    let entry = entry.as_ref().unwrap();
-                    entry.path().unwrap().to_str() == Some("data.tar.gz")
-                })
-                .context("data.tar.gz")??;
+        pub fn license(mut self, license: impl Into<String>) -> Self {
+            self.spec.licenses.push(license.into());
+            self
+        }
+
+        pub fn platform(mut self, platform: Platform) -> Self {
+            self.spec.platform = platform;
+            self
+        }
+
+        pub fn homepage(mut self, homepage: impl Into<String>) -> Self {
+            self.spec.homepage = homepage.into();
+            self
+        }
+
+        pub fn files<I, S>(mut self, files: I) -> Self
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<String>,
+        {
+            self.spec.files = files.into_iter().map(Into::into).collect();
+            self
+        }
+
+        /// Defaults to midnight today, since RubyGems only records the day.
+        pub fn date(mut self, date: DateTime<chrono::Utc>) -> Self {
+            self.date = Some(date);
+            self
+        }
 
-            let reader = flate2::bufread::GzDecoder::new(BufReader::new(entry));
-            let mut archive = tar::Archive::new(reader);
-            let entries = archive.entries()?;
-            for entry in entries {
-                let mut entry = entry?;
-                f(&mut entry)?;
+        /// Checks the fields RubyGems requires and fills in `>= 0` ruby and
+        /// rubygems requirements, `lib` as the require path, `bin` as the
+        /// bindir, and the current specification version.
+        pub fn build(self) -> Result<Specification, Error> {
+            let mut spec = self.spec;
+            if spec.name.is_empty() {
+                return Err(Error::Specification("missing a name".to_string()));
+            }
+            if !spec
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+            {
+                return Err(Error::Specification(format!(
+                    "invalid name {:?}",
+                    spec.name
+                )));
+            }
+            if spec.version.segments.is_empty() {
+                return Err(Error::Specification(format!(
+                    "{} is missing a version",
+                    spec.name
+                )));
+            }
+            if spec.summary.is_empty() {
+                return Err(Error::Specification(format!(
+                    "{} is missing a summary",
+                    spec.name
+                )));
+            }
+            if spec.authors.is_empty() {
+                return Err(Error::Specification(format!(
+                    "{} is missing authors",
+                    spec.name
+                )));
             }
 
-            Ok(())
+            spec.required_ruby_version
+                .get_or_insert_with(Requirement::default);
+            spec.required_rubygems_version
+                .get_or_insert_with(Requirement::default);
+            if spec.require_paths.is_empty() {
+                spec.require_paths.push("lib".to_string());
+            }
+            spec.bindir.get_or_insert_with(|| "bin".to_string());
+            spec.specification_version = 4;
+            spec.date = self.date.unwrap_or_else(|| {
+                chrono::Utc::now()
+                    .date_naive()
+                    .and_time(chrono::NaiveTime::MIN)
+                    .and_utc()
+            });
+            Ok(spec)
         }
     }
 
-    #[serde_as]
-    #[derive(Debug, PartialEq, Eq, Serialize)]
-    pub struct PackageEntry<'a> {
-        pub gem: &'a str,
-        pub version: &'a str,
-        pub platform: &'a str,
-        pub size: u64,
-        pub path: &'a str,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub link_name: Option<&'a str>,
-        pub mode: u32,
-        #[serde(skip_serializing)]
-        pub uid: u64,
-        #[serde(skip_serializing)]
-        pub gid: u64,
-        pub mtime: u64,
-        #[serde_as(as = "serde_with::hex::Hex")]
-        pub sha256: GenericArray<u8, <sha2::Sha256 as sha2::digest::OutputSizeUser>::OutputSize>,
-        pub magic: &'a str,
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Platform(String);
+
+    impl Serialize for Platform {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_str(if self.0.is_empty() { "ruby" } else { &self.0 })
+        }
+    }
+
+    /// Missing, `null` and empty platforms are `ruby`, as RubyGems treats
+    /// them. Other strings are kept as written, so `full_name` still matches
+    /// the gem's file name, but must be made of valid platform characters.
+    impl<'de> Deserialize<'de> for Platform {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let platform = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+            if platform.is_empty() {
+                return Ok(Platform::default());
+            }
+            if platform.split('-').any(str::is_empty)
+                || !platform
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            {
+                return Err(serde::de::Error::custom(format!(
+                    "invalid platform {platform:?}"
+                )));
+            }
+            Ok(Platform(platform))
+        }
+    }
+
+    impl Platform {
+        /// Wraps `platform` without validating or normalizing it. Prefer
+        /// `str::parse` unless the string is already known to be canonical.
+        pub fn new<T: AsRef<str>>(platform: T) -> Self {
+            Platform(platform.as_ref().to_string())
+        }
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        /// The platform's CPU, normalized the way `Gem::Platform` does, so
+        /// `i686-linux` is `x86`. `universal-darwin` has the CPU `universal`,
+        /// which matches any other. `ruby`, bare OSes like `java`, and
+        /// platforms that don't parse have none.
+        pub fn cpu(&self) -> Option<&str> {
+            parse_platform(&self.0).ok()?.0
+        }
+
+        /// The platform's OS, such as `linux` or `java`. `ruby` isn't tied to
+        /// an OS, so it has none, as do platforms that don't parse.
+        pub fn os(&self) -> Option<&str> {
+            match parse_platform(&self.0).ok()? {
+                (_, "ruby", _) => None,
+                (_, os, _) => Some(os),
+            }
+        }
+
+        /// The OS version or libc, such as `23` in `arm64-darwin-23` or
+        /// `musl` in `x86_64-linux-musl`.
+        pub fn version(&self) -> Option<&str> {
+            parse_platform(&self.0).ok()?.2
+        }
+
+        /// The platform this crate was compiled for, named as RubyGems names
+        /// it, so `arm64-darwin` on Apple silicon and `x64-mingw-ucrt` on
+        /// 64-bit Windows. Any `wasm32` target is `wasm32-wasi`, the only
+        /// WebAssembly platform Ruby builds for. Unlike `Gem::Platform.local`,
+        /// there's no OS version, since that'd only be known at runtime.
+        pub fn local() -> Platform {
+            use std::env::consts::{ARCH, OS};
+
+            if ARCH == "wasm32" {
+                return Platform::from(KnownPlatform::Wasm32Wasi);
+            }
+            let cpu = match (ARCH, OS) {
+                ("aarch64", "macos") => "arm64",
+                ("x86_64", "windows") => "x64",
+                (arch, _) => arch,
+            };
+            let os = match OS {
+                "macos" => "darwin",
+                "windows" => "mingw-ucrt",
+                "linux" if cfg!(target_env = "musl") => "linux-musl",
+                os => os,
+            };
+            Platform(format!("{cpu}-{os}"))
+        }
+
+        /// Returns the well-known platform this exactly names, if any.
+        /// Versioned platforms like `arm64-darwin-23` aren't recognized.
+        pub fn known(&self) -> Option<KnownPlatform> {
+            self.0.parse().ok()
+        }
+
+        /// Scores how specifically this platform matches `target`, following
+        /// `Gem::Platform#===`. Higher is more specific, `ruby` matches
+        /// everything with a score of 0, and `None` means no match.
+        pub fn specificity(&self, target: &Platform) -> Option<u32> {
+            if self.0 == "ruby" {
+                return Some(0);
+            }
+            let (cpu, os, version) = parse_platform(&self.0).ok()?;
+            let (target_cpu, target_os, target_version) = parse_platform(&target.0).ok()?;
+            if os != target_os {
+                return None;
+            }
+
+            let wildcard = |cpu: Option<&str>| matches!(cpu, None | Some("universal"));
+            let cpu_score = match (cpu, target_cpu) {
+                (Some(cpu), Some(target_cpu)) if cpu == target_cpu => 2,
+                (Some("arm"), Some(target_cpu)) if target_cpu.starts_with("armv") => 1,
+                (cpu, target_cpu) if wildcard(cpu) || wildcard(target_cpu) => 0,
+                _ => return None,
+            };
+
+            let version_score = if os == "linux" {
+                if normalized_linux_version(version) != normalized_linux_version(target_version) {
+                    return None;
+                }
+                u32::from(version.is_some() && version == target_version)
+            } else {
+                match (version, target_version) {
+                    (Some(version), Some(target_version)) if version == target_version => 1,
+                    (None, _) | (_, None) => 0,
+                    _ => return None,
+                }
+            };
+
+            Some(1 + cpu_score + version_score)
+        }
+    }
+
+    impl Default for Platform {
+        fn default() -> Self {
+            Platform("ruby".to_string())
+        }
+    }
+
+    impl Display for Platform {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    /// Platforms gems are commonly published for, as named by RubyGems and
+    /// rake-compiler-dock.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString, StrumDisplay)]
+    pub enum KnownPlatform {
+        #[strum(serialize = "ruby")]
+        Ruby,
+        #[strum(serialize = "java")]
+        Java,
+        #[strum(serialize = "universal-java")]
+        UniversalJava,
+        #[strum(serialize = "x86_64-linux")]
+        X86_64Linux,
+        #[strum(serialize = "x86_64-linux-gnu")]
+        X86_64LinuxGnu,
+        #[strum(serialize = "x86_64-linux-musl")]
+        X86_64LinuxMusl,
+        #[strum(serialize = "aarch64-linux")]
+        Aarch64Linux,
+        #[strum(serialize = "aarch64-linux-gnu")]
+        Aarch64LinuxGnu,
+        #[strum(serialize = "aarch64-linux-musl")]
+        Aarch64LinuxMusl,
+        #[strum(serialize = "arm-linux")]
+        ArmLinux,
+        #[strum(serialize = "arm-linux-gnu")]
+        ArmLinuxGnu,
+        #[strum(serialize = "arm-linux-musl")]
+        ArmLinuxMusl,
+        #[strum(serialize = "x86-linux")]
+        X86Linux,
+        #[strum(serialize = "x86-linux-gnu")]
+        X86LinuxGnu,
+        #[strum(serialize = "x86-linux-musl")]
+        X86LinuxMusl,
+        #[strum(serialize = "x86_64-darwin")]
+        X86_64Darwin,
+        #[strum(serialize = "arm64-darwin")]
+        Arm64Darwin,
+        #[strum(serialize = "universal-darwin")]
+        UniversalDarwin,
+        #[strum(serialize = "x86-mingw32")]
+        X86Mingw32,
+        #[strum(serialize = "x64-mingw32")]
+        X64Mingw32,
+        #[strum(serialize = "x64-mingw-ucrt")]
+        X64MingwUcrt,
+        #[strum(serialize = "aarch64-mingw-ucrt")]
+        Aarch64MingwUcrt,
+        #[strum(serialize = "x86-mswin32")]
+        X86Mswin32,
+        #[strum(serialize = "x64-mswin64")]
+        X64Mswin64,
+        #[strum(serialize = "wasm32-wasi")]
+        Wasm32Wasi,
+        #[strum(serialize = "wasm32-p1-wasi")]
+        Wasm32P1Wasi,
+    }
+
+    impl From<KnownPlatform> for Platform {
+        fn from(platform: KnownPlatform) -> Self {
+            Platform(platform.to_string())
+        }
+    }
+
+    /// Operating systems `Gem::Platform` recognizes, and whether they can carry
+    /// a version. Longer names come first so `mingw32` isn't read as `mingw`.
+    const PLATFORM_OSES: &[(&str, &str, bool)] = &[
+        ("aix", "aix", true),
+        ("cygwin", "cygwin", false),
+        ("darwin", "darwin", true),
+        ("macruby", "macruby", false),
+        ("freebsd", "freebsd", true),
+        ("jruby", "java", false),
+        ("java", "java", true),
+        ("dalvik", "dalvik", true),
+        ("dotnet", "dotnet", true),
+        ("linux", "linux", true),
+        ("mingw32", "mingw32", false),
+        ("mingw", "mingw", true),
+        ("mswin32", "mswin32", true),
+        ("mswin64", "mswin64", true),
+        ("netbsdelf", "netbsdelf", false),
+        ("openbsd", "openbsd", true),
+        ("solaris", "solaris", true),
+        ("wasi", "wasi", false),
+    ];
+
+    impl FromStr for Platform {
+        type Err = Error;
+
+        /// Parses a `cpu-os[-version]` platform the way `Gem::Platform.new`
+        /// does, normalizing CPU aliases.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (cpu, os, version) =
+                parse_platform(s).map_err(|e| Error::PlatformParse(e.to_string()))?;
+            let parts = match (os, version) {
+                ("wasi", Some(preview)) => [cpu, Some(preview), Some(os)],
+                _ => [cpu, Some(os), version],
+            };
+            Ok(Platform(
+                parts.into_iter().flatten().collect::<Vec<_>>().join("-"),
+            ))
+        }
+    }
+
+    /// `gnu`, `gnueabi` and `gnueabihf` all mean the default libc.
+    fn normalized_linux_version(version: Option<&str>) -> Option<&str> {
+        let version = version?;
+        let version = version.strip_prefix("gnu").unwrap_or(version);
+        let version = version
+            .strip_suffix("eabihf")
+            .or(version.strip_suffix("eabi"))
+            .unwrap_or(version);
+        (!version.is_empty()).then_some(version)
+    }
+
+    /// Splits a platform string into its normalized `(cpu, os, version)`.
+    fn parse_platform(s: &str) -> anyhow::Result<(Option<&str>, &str, Option<&str>)> {
+        if s.is_empty() {
+            bail!("Empty platform string");
+        }
+        if s == "ruby" {
+            return Ok((None, "ruby", None));
+        }
+        if let Some(c) = s
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')))
+        {
+            bail!("Invalid character {c:?} in platform {s:?}");
+        }
+        if s.split('-').any(str::is_empty) {
+            bail!("Empty segment in platform {s:?}");
+        }
+
+        let (cpu, os_and_version) = match s.split_once('-') {
+            Some((cpu, rest)) => (Some(cpu), rest),
+            None => (None, s),
+        };
+        // WASI puts its preview version first, as in `wasm32-p1-wasi`.
+        if let Some((preview, "wasi")) = os_and_version.split_once('-')
+            && preview
+                .strip_prefix('p')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        {
+            return Ok((cpu, "wasi", Some(preview)));
+        }
+        let (os, has_version, rest) = PLATFORM_OSES
+            .iter()
+            .find_map(|(prefix, os, has_version)| {
+                os_rest(os_and_version, prefix).map(|rest| (*os, *has_version, rest))
+            })
+            .with_context(|| format!("Unknown os in platform {s:?}"))?;
+        let version = match rest.strip_prefix(['-', '_']).unwrap_or(rest) {
+            "" => None,
+            version if has_version => Some(version),
+            version => bail!("Unexpected version {version:?} for {os} in platform {s:?}"),
+        };
+
+        let cpu = match cpu {
+            Some(cpu) if cpu.len() == 4 && cpu.starts_with('i') && cpu.ends_with("86") => {
+                Some("x86")
+            }
+            // RubyGems names 64-bit Windows `x64`, so it's kept there.
+            Some("x64") if matches!(os, "mingw" | "mingw32" | "mswin32" | "mswin64") => Some("x64"),
+            Some("x64" | "amd64") => Some("x86_64"),
+            None if os == "mswin32" => Some("x86"),
+            cpu => cpu,
+        };
+
+        Ok((cpu, os, version))
+    }
+
+    fn os_rest<'a>(os: &'a str, prefix: &str) -> Option<&'a str> {
+        os.strip_prefix(prefix).filter(|rest| {
+            rest.is_empty()
+                || rest.starts_with(['-', '_'])
+                || rest.starts_with(|c: char| c.is_ascii_digit())
+        })
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum VersionSegment {
+        Number(u64),
+        String(String),
+    }
+    /// Compares like `Gem::Version#<=>`, so `1.0` and `1.0.0` are equal.
+    ///
+    /// `==` and `cmp` are semantic, so `1.0` and `1.0.0` are the same
+    /// `BTreeMap` key. To tell them apart by the string as written, use
+    /// `eq_exact` for equality and `total_cmp` for ordering.
+    #[derive(Debug, Clone, Default)]
+    pub struct Version {
+        version: String,
+        segments: Vec<VersionSegment>,
+    }
+
+    impl Version {
+        pub fn as_str(&self) -> &str {
+            &self.version
+        }
+
+        /// The version as written, which is also what `Serialize` emits.
+        pub fn to_plain_string(&self) -> String {
+            self.version.clone()
+        }
+
+        /// Serializes as the `!ruby/object:Gem::Version` mapping Psych
+        /// writes, for use with `#[serde(serialize_with = "...")]`. Formats
+        /// without tags, like JSON, get a map from the tag to the mapping.
+        pub fn serialize_ruby_object<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            use serde_yaml::value::{Tag, TaggedValue};
+
+            let mut mapping = serde_yaml::Mapping::new();
+            mapping.insert("version".into(), self.version.clone().into());
+            TaggedValue {
+                tag: Tag::new("ruby/object:Gem::Version"),
+                value: serde_yaml::Value::Mapping(mapping),
+            }
+            .serialize(serializer)
+        }
+
+        /// Parses a version as written in a git tag, ignoring surrounding
+        /// whitespace and one leading `v` or `V`, so `v1.2.3` is `1.2.3`.
+        /// Everything else is as strict as `from_str`.
+        pub fn parse_lenient(s: &str) -> Result<Version, Error> {
+            let trimmed = s.trim();
+            let unprefixed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+            Version::from_str(unprefixed)
+        }
+
+        /// Parses every string, keeping going past malformed ones, which are
+        /// returned alongside their errors.
+        pub fn parse_all<S: AsRef<str>>(
+            versions: impl IntoIterator<Item = S>,
+        ) -> (Vec<Version>, Vec<(String, Error)>) {
+            let mut parsed = Vec::new();
+            let mut failed = Vec::new();
+            for version in versions {
+                let version = version.as_ref();
+                match Version::from_str(version) {
+                    Ok(v) => parsed.push(v),
+                    Err(e) => failed.push((version.to_string(), e)),
+                }
+            }
+            (parsed, failed)
+        }
+
+        /// Matches `Gem::Version#bump`: drops any prerelease segments and the
+        /// last release segment, then increments the new last segment.
+        pub fn bump(&self) -> Version {
+            let mut segments = self.release_numbers();
+            if segments.len() > 1 {
+                segments.pop();
+            }
+            match segments.last_mut() {
+                Some(last) => *last += 1,
+                None => segments.push(1),
+            }
+
+            Version::from_segments(segments.into_iter().map(VersionSegment::Number).collect())
+        }
+
+        /// The lowest prerelease of `bump`, so `1.2.3` yields `1.3.A`. It's
+        /// the exclusive upper bound of `~>`, which rejects the prereleases
+        /// of its bump along with the bump itself. String segments compare
+        /// bytewise and are runs of letters, so none sorts below `A`.
+        fn bump_prerelease(&self) -> Version {
+            let mut segments = self.bump().segments;
+            segments.push(VersionSegment::String("A".to_string()));
+            Version::from_segments(segments)
+        }
+
+        /// Drops trailing `.0` segments, keeping at least one, so `1.2.0.0`
+        /// becomes `1.2`. Prereleases are returned unchanged, since any zeros
+        /// after their string segments are part of the prerelease.
+        pub fn canonicalize(&self) -> Version {
+            if self.is_prerelease() {
+                return self.clone();
+            }
+            let mut segments = self.segments.clone();
+            while segments.len() > 1 && segments.last() == Some(&VersionSegment::Number(0)) {
+                segments.pop();
+            }
+            Version::from_segments(segments)
+        }
+
+        pub fn is_prerelease(&self) -> bool {
+            self.segments
+                .iter()
+                .any(|segment| matches!(segment, VersionSegment::String(_)))
+        }
+
+        /// The numeric segments before the first string segment, so `1.0.0`
+        /// for `1.0.0.rc.1`.
+        pub fn release_segments(&self) -> &[VersionSegment] {
+            &self.segments[..self.prerelease_start()]
+        }
+
+        /// The segments from the first string segment on, so `rc.1` for
+        /// `1.0.0.rc.1`, or nothing for a release.
+        pub fn prerelease_segments(&self) -> &[VersionSegment] {
+            &self.segments[self.prerelease_start()..]
+        }
+
+        fn prerelease_start(&self) -> usize {
+            self.segments
+                .iter()
+                .position(|segment| matches!(segment, VersionSegment::String(_)))
+                .unwrap_or(self.segments.len())
+        }
+
+        /// The leading numbers of `split_segments`, so `1, 2, 3` for both
+        /// `1.2.3.a` and `1.2.3a`.
+        fn release_numbers(&self) -> Vec<u64> {
+            self.split_segments()
+                .into_iter()
+                .map_while(|segment| match segment {
+                    VersionSegment::Number(number) => Some(number),
+                    VersionSegment::String(_) => None,
+                })
+                .collect()
+        }
+
+        /// Matches `Gem::Version#release`, so `1.2.3` for `1.2.3a`.
+        pub fn release(&self) -> Version {
+            Version::from_segments(
+                self.release_numbers()
+                    .into_iter()
+                    .map(VersionSegment::Number)
+                    .collect(),
+            )
+        }
+
+        /// The first two release segments, e.g. `(Some(1), Some(2))` for
+        /// `1.2.3` and `(Some(1), None)` for `1`, for grouping by series.
+        pub fn minor_series(&self) -> (Option<u64>, Option<u64>) {
+            let numbers = self.release_numbers();
+            (numbers.first().copied(), numbers.get(1).copied())
+        }
+
+        /// Keeps the first `n` segments, so `1.2` for `1.2.3.rc1` and `n` of
+        /// 2. Versions with fewer segments are returned unchanged. Panics if
+        /// `n` is 0, since a version needs at least one segment.
+        pub fn truncate(&self, n: usize) -> Version {
+            assert!(n >= 1, "can't truncate {} to no segments", self.version);
+            Version::from_segments(self.segments.iter().take(n).cloned().collect())
+        }
+
+        /// The release with `tag` as its prerelease, replacing any it had,
+        /// so `1.2.0.beta` for `1.2.0.rc.2` and `beta`. A dotted `tag` like
+        /// `pre.1` is split into its segments. Panics unless `tag` is
+        /// alphanumeric segments starting with a non-number, since anything
+        /// else wouldn't parse back as a prerelease of the release.
+        pub fn with_prerelease(&self, tag: &str) -> Version {
+            assert!(is_prerelease_tag(tag), "invalid prerelease tag {tag:?}");
+            let mut segments = self.release_segments().to_vec();
+            segments.extend(tag.split('.').map(|segment| match segment.parse() {
+                Ok(number) => VersionSegment::Number(number),
+                Err(_) => VersionSegment::String(segment.to_string()),
+            }));
+            Version::from_segments(segments)
+        }
+
+        /// Steps to the next `<tag>.<n>` prerelease of the release: `1.2.0`
+        /// goes to `1.2.0.pre.1`, then `1.2.0.pre.2`. A prerelease with a
+        /// different tag, or none of the `<tag>.<n>` form, starts over at 1.
+        /// Panics unless `tag` is a single segment `with_prerelease` takes.
+        pub fn next_prerelease(&self, tag: &str) -> Version {
+            assert!(
+                is_prerelease_tag(tag) && !tag.contains('.'),
+                "invalid prerelease tag {tag:?}"
+            );
+            let n = match self.prerelease_segments() {
+                [VersionSegment::String(current), VersionSegment::Number(n)] if current == tag => {
+                    n + 1
+                }
+                _ => 1,
+            };
+            let mut segments = self.release_segments().to_vec();
+            segments.push(VersionSegment::String(tag.to_string()));
+            segments.push(VersionSegment::Number(n));
+            Version::from_segments(segments)
+        }
+
+        /// Matches `Gem::Version#_segments`: splits `1.0a1` into `1, 0, "a",
+        /// 1`, on letter/digit boundaries as well as dots.
+        fn split_segments(&self) -> Vec<VersionSegment> {
+            let version = self.version.replace('-', ".pre.");
+            let mut segments = Vec::new();
+            let mut chars = version.char_indices().peekable();
+            while let Some((start, c)) = chars.next() {
+                if !c.is_ascii_alphanumeric() {
+                    continue;
+                }
+                let mut end = start + c.len_utf8();
+                while let Some(&(idx, next)) = chars.peek() {
+                    if next.is_ascii_digit() != c.is_ascii_digit() || !next.is_ascii_alphanumeric()
+                    {
+                        break;
+                    }
+                    end = idx + next.len_utf8();
+                    chars.next();
+                }
+                let run = &version[start..end];
+                segments.push(match run.parse::<u64>() {
+                    Ok(number) => VersionSegment::Number(number),
+                    Err(_) => VersionSegment::String(run.to_string()),
+                });
+            }
+            segments
+        }
+
+        /// Matches `Gem::Version#canonical_segments`: `split_segments` with
+        /// trailing zeros dropped from both the release and prerelease parts.
+        fn canonical_segments(&self) -> Vec<VersionSegment> {
+            let mut segments = self.split_segments();
+            let string_start = segments
+                .iter()
+                .position(|segment| matches!(segment, VersionSegment::String(_)))
+                .unwrap_or(segments.len());
+            let mut prerelease = segments.split_off(string_start);
+            for part in [&mut segments, &mut prerelease] {
+                while matches!(part.last(), Some(VersionSegment::Number(0))) {
+                    part.pop();
+                }
+            }
+            segments.append(&mut prerelease);
+            segments
+        }
+
+        fn from_segments(segments: Vec<VersionSegment>) -> Version {
+            let version = segments
+                .iter()
+                .map(|segment| match segment {
+                    VersionSegment::Number(number) => number.to_string(),
+                    VersionSegment::String(string) => string.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(".");
+            Version { version, segments }
+        }
+    }
+
+    /// Whether `tag` can follow a release as its prerelease: non-empty
+    /// alphanumeric segments, the first of them not a number.
+    fn is_prerelease_tag(tag: &str) -> bool {
+        tag.split('.')
+            .next()
+            .is_some_and(|first| first.parse::<u64>().is_err())
+            && tag.split('.').all(|segment| {
+                !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_alphanumeric())
+            })
+    }
+
+    impl PartialEq for Version {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other).is_eq()
+        }
+    }
+
+    impl Eq for Version {}
+
+    impl PartialOrd for Version {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Version {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            use std::cmp::Ordering;
+
+            if self.version == other.version {
+                return Ordering::Equal;
+            }
+            let lhs = self.canonical_segments();
+            let rhs = other.canonical_segments();
+            let zero = VersionSegment::Number(0);
+            for idx in 0..lhs.len().max(rhs.len()) {
+                let ordering = match (lhs.get(idx).unwrap_or(&zero), rhs.get(idx).unwrap_or(&zero))
+                {
+                    (VersionSegment::Number(l), VersionSegment::Number(r)) => l.cmp(r),
+                    (VersionSegment::String(l), VersionSegment::String(r)) => l.cmp(r),
+                    (VersionSegment::String(_), VersionSegment::Number(_)) => Ordering::Less,
+                    (VersionSegment::Number(_), VersionSegment::String(_)) => Ordering::Greater,
+                };
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        }
+    }
+
+    impl Version {
+        /// Orders like `cmp`, then breaks ties between versions RubyGems
+        /// considers equal, like `1.0` and `1.0.0`, by their strings. Use it
+        /// where the order must be deterministic, such as sorted output; it
+        /// is only `Equal` for identical strings.
+        pub fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.cmp(other)
+                .then_with(|| self.version.cmp(&other.version))
+        }
+
+        /// Whether the versions are written identically, so unlike `==`,
+        /// `1.0` and `1.0.0` differ. Agrees with `total_cmp` being `Equal`.
+        pub fn eq_exact(&self, other: &Version) -> bool {
+            self.version == other.version
+        }
+    }
+
+    impl Display for Version {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.version)
+        }
+    }
+
+    /// Serializes as the plain version string, e.g. `"1.2.3"`. Use
+    /// `Version::serialize_ruby_object` where the `Gem::Version` form is
+    /// expected.
+    impl Serialize for Version {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_str(&self.version)
+        }
+    }
+
+    /// Accepts both the plain string and the `!ruby/object:Gem::Version`
+    /// mapping Psych writes.
+    impl<'de> Deserialize<'de> for Version {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let value = serde_yaml::Value::deserialize(deserializer)?;
+            version_from_yaml(value).map_err(serde::de::Error::custom)
+        }
+    }
+
+    fn untag_yaml(value: serde_yaml::Value) -> serde_yaml::Value {
+        match value {
+            serde_yaml::Value::Tagged(tagged) => untag_yaml(tagged.value),
+            value => value,
+        }
+    }
+
+    /// Reads a version from a string, a number such as an unquoted `1.0`, or
+    /// a mapping with a `version` key, tagged or not. A tag that went through
+    /// a format without tags, as a `{"!ruby/object:Gem::Version": ...}` map,
+    /// is accepted too.
+    fn version_from_yaml(value: serde_yaml::Value) -> Result<Version, Error> {
+        use serde_yaml::Value;
+
+        match untag_yaml(value) {
+            Value::String(version) => Version::from_str(&version),
+            Value::Number(version) => Version::from_str(&version.to_string()),
+            Value::Mapping(mapping)
+                if mapping.len() == 1
+                    && mapping
+                        .keys()
+                        .all(|key| key.as_str() == Some("!ruby/object:Gem::Version")) =>
+            {
+                version_from_yaml(mapping.into_iter().next().unwrap().1)
+            }
+            Value::Mapping(mut mapping) => match mapping.remove("version").map(untag_yaml) {
+                Some(value @ (Value::String(_) | Value::Number(_))) => version_from_yaml(value),
+                _ => Err(Error::VersionParse(format!(
+                    "expected a version string in {mapping:?}"
+                ))),
+            },
+            value => Err(Error::VersionParse(format!(
+                "expected a Gem::Version, got {value:?}"
+            ))),
+        }
+    }
+
+    /// Parses the `Gem::Version` grammar: a numeric first segment, then
+    /// alphanumeric segments separated by single dots, optionally followed by
+    /// a `-` and dot-separated `[0-9A-Za-z-]` segments. As in RubyGems, every
+    /// `-` is rewritten to `.pre.`, so `1.0-rc1` is the prerelease
+    /// `1.0.pre.rc1`. Surrounding whitespace is ignored.
+    impl FromStr for Version {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid =
+                |reason: &str| Error::VersionParse(format!("{reason} in version string {s:?}"));
+            let trimmed = s.trim();
+            let (release, prerelease) = match trimmed.split_once('-') {
+                Some((release, prerelease)) => (release, Some(prerelease)),
+                None => (trimmed, None),
+            };
+            for (idx, segment) in release.split('.').enumerate() {
+                if segment.is_empty() {
+                    return Err(invalid("empty segment"));
+                }
+                if idx == 0 && !segment.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid("non-numeric first segment"));
+                }
+                if !segment.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(invalid("invalid character"));
+                }
+            }
+            for segment in prerelease.into_iter().flat_map(|p| p.split('.')) {
+                if segment.is_empty() {
+                    return Err(invalid("empty segment"));
+                }
+                if !segment
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                {
+                    return Err(invalid("invalid character"));
+                }
+            }
+
+            let version = trimmed.replace('-', ".pre.");
+            let segments = version
+                .split('.')
+                .map(|segment| {
+                    if let Ok(number) = segment.parse::<u64>() {
+                        VersionSegment::Number(number)
+                    } else {
+                        VersionSegment::String(segment.to_string())
+                    }
+                })
+                .collect();
+            Ok(Version { version, segments })
+        }
+    }
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Dependency {
+        name: String,
+        requirement: Requirement,
+        r#type: DependencyType,
+        /// RubyGems doesn't record this, so it's only set by callers that
+        /// know a dependency is specific to one platform variant of a gem.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        platform: Option<Platform>,
+    }
+    impl Dependency {
+        pub fn new(name: String, requirement: Requirement, r#type: DependencyType) -> Self {
+            Dependency {
+                name,
+                requirement,
+                r#type,
+                platform: None,
+            }
+        }
+        pub fn with_platform(mut self, platform: Platform) -> Self {
+            self.platform = Some(platform);
+            self
+        }
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+        pub fn requirement(&self) -> &Requirement {
+            &self.requirement
+        }
+        pub fn r#type(&self) -> DependencyType {
+            self.r#type
+        }
+        pub fn platform(&self) -> Option<&Platform> {
+            self.platform.as_ref()
+        }
+
+        /// Formats the dependency the way Bundler writes it in a
+        /// `Gemfile.lock`, e.g. `rails (>= 7.0, < 8.0)`.
+        pub fn to_lock_string(&self) -> String {
+            if self.requirement.is_none() {
+                return self.name.clone();
+            }
+            let mut requirements: Vec<String> = self
+                .requirement
+                .requirements
+                .iter()
+                .map(|(op, version)| format!("{op} {version}"))
+                .collect();
+            requirements.sort();
+            requirements.reverse();
+            format!("{} ({})", self.name, requirements.join(", "))
+        }
+
+        /// Serializes the dependency as the `!ruby/object:Gem::Dependency`
+        /// document Psych emits, with both the `requirement` and the legacy
+        /// `version_requirements` ivars.
+        pub fn to_ruby_yaml(&self) -> String {
+            let mut yaml = String::from("--- !ruby/object:Gem::Dependency\n");
+            yaml.push_str(&format!("name: {}\n", psych_scalar(&self.name)));
+            yaml.push_str("requirement: ");
+            self.requirement.write_ruby_yaml(&mut yaml);
+            yaml.push_str(&format!("type: {}\n", self.r#type));
+            yaml.push_str("prerelease: false\n");
+            yaml.push_str("version_requirements: ");
+            self.requirement.write_ruby_yaml(&mut yaml);
+            yaml
+        }
+    }
+
+    /// Writes `s` as a plain YAML scalar, single-quoted the way Psych does when
+    /// it would otherwise load as a number, boolean or null, like `'13.0'`,
+    /// `'1234'` or `'yes'`.
+    fn psych_scalar(s: &str) -> String {
+        // YAML 1.1 numbers may have `_` separators and a radix prefix.
+        let number = s.replace('_', "");
+        let ambiguous = s.is_empty()
+            || number.parse::<f64>().is_ok()
+            || [("0x", 16), ("0o", 8), ("0b", 2)]
+                .iter()
+                .any(|(prefix, radix)| {
+                    number
+                        .strip_prefix(prefix)
+                        .is_some_and(|digits| u64::from_str_radix(digits, *radix).is_ok())
+                })
+            || matches!(
+                s.to_ascii_lowercase().as_str(),
+                "~" | "null"
+                    | "y"
+                    | "yes"
+                    | "n"
+                    | "no"
+                    | "true"
+                    | "false"
+                    | "on"
+                    | "off"
+                    | ".inf"
+                    | "-.inf"
+                    | "+.inf"
+                    | ".nan"
+            );
+        if ambiguous {
+            format!("'{}'", s.replace('\'', "''"))
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// Displays and parses as the `:runtime`/`:development` symbols, the same
+    /// form serde uses.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum DependencyType {
+        #[serde(rename = ":runtime")]
+        Runtime,
+        #[serde(rename = ":development")]
+        Development,
+    }
+
+    impl DependencyType {
+        pub const ALL: [DependencyType; 2] = [DependencyType::Runtime, DependencyType::Development];
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                DependencyType::Runtime => ":runtime",
+                DependencyType::Development => ":development",
+            }
+        }
+    }
+
+    impl Display for DependencyType {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    impl FromStr for DependencyType {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            DependencyType::ALL
+                .into_iter()
+                .find(|r#type| r#type.as_str() == s)
+                .ok_or_else(|| Error::DependencyType(s.to_string()))
+        }
+    }
+    /// Compares and serializes its constraints in normalized order, so that
+    /// `>= 1.0, < 2.0` and `< 2.0, >= 1.0` are the same requirement.
+    #[derive(Debug, Clone, Eq)]
+    pub struct Requirement {
+        requirements: Vec<(RequirementOperator, Version)>,
+    }
+
+    impl PartialEq for Requirement {
+        fn eq(&self, other: &Self) -> bool {
+            self.sorted() == other.sorted()
+        }
+    }
+
+    /// Accepts the shape Psych gives a `Gem::Requirement`: a mapping whose
+    /// `requirements` are `[operator, version]` pairs, each version being a
+    /// `Gem::Version` object or a bare string. The pairs are also accepted on
+    /// their own, without the mapping around them.
+    impl<'de> Deserialize<'de> for Requirement {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            use serde::de::Error as _;
+            use serde_yaml::Value;
+
+            let pairs = match untag_yaml(Value::deserialize(deserializer)?) {
+                Value::Mapping(mut mapping) => mapping
+                    .remove("requirements")
+                    .map(untag_yaml)
+                    .ok_or_else(|| D::Error::missing_field("requirements"))?,
+                value => value,
+            };
+            let Value::Sequence(pairs) = pairs else {
+                return Err(D::Error::custom(format!(
+                    "expected a sequence of requirements, got {pairs:?}"
+                )));
+            };
+            let requirements = pairs
+                .into_iter()
+                .map(|pair| {
+                    let Value::Sequence(pair) = untag_yaml(pair) else {
+                        return Err(D::Error::custom("expected an [operator, version] pair"));
+                    };
+                    let [op, v] = <[Value; 2]>::try_from(pair).map_err(|pair| {
+                        D::Error::custom(format!(
+                            "expected an [operator, version] pair, got {} elements",
+                            pair.len()
+                        ))
+                    })?;
+                    let op = RequirementOperator::deserialize(untag_yaml(op))
+                        .map_err(D::Error::custom)?;
+                    Ok((op, version_from_yaml(v).map_err(D::Error::custom)?))
+                })
+                .collect::<Result<_, D::Error>>()?;
+            Ok(Requirement { requirements })
+        }
+    }
+
+    impl Serialize for Requirement {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            use serde::ser::SerializeStruct;
+            let mut state = serializer.serialize_struct("Requirement", 1)?;
+            state.serialize_field("requirements", &self.sorted())?;
+            state.end()
+        }
+    }
+
+    /// Matches `Gem::Requirement.default`: `>= 0`.
+    impl Default for Requirement {
+        fn default() -> Self {
+            Requirement::at_least(Version::from_segments(vec![VersionSegment::Number(0)]))
+        }
+    }
+
+    impl Requirement {
+        pub fn new(requirements: Vec<(RequirementOperator, Version)>) -> Self {
+            Requirement { requirements }
+        }
+
+        /// `= version`.
+        ///
+        /// ```
+        /// # use gemspec_rs::gem::Requirement;
+        /// let requirement = Requirement::exactly("1.2.3".parse().unwrap());
+        /// assert_eq!(requirement.to_string(), "= 1.2.3");
+        /// ```
+        pub fn exactly(version: Version) -> Self {
+            Requirement::new(vec![(RequirementOperator::Equal, version)])
+        }
+
+        /// `>= version`.
+        ///
+        /// ```
+        /// # use gemspec_rs::gem::Requirement;
+        /// let requirement = Requirement::at_least("1.2".parse().unwrap());
+        /// assert_eq!(requirement.to_string(), ">= 1.2");
+        /// ```
+        pub fn at_least(version: Version) -> Self {
+            Requirement::new(vec![(RequirementOperator::GreaterThanOrEqual, version)])
+        }
+
+        /// `~> version`, allowing anything up to the next bump of `version`.
+        ///
+        /// ```
+        /// # use gemspec_rs::gem::Requirement;
+        /// let requirement = Requirement::pessimistic("2.0".parse().unwrap());
+        /// assert_eq!(requirement.to_string(), "~> 2.0");
+        /// ```
+        pub fn pessimistic(version: Version) -> Self {
+            Requirement::new(vec![(RequirementOperator::Tilde, version)])
+        }
+        pub fn requirements(&self) -> &[(RequirementOperator, Version)] {
+            &self.requirements
+        }
+
+        /// Returns a copy with the constraints sorted by operator, then
+        /// version.
+        pub fn normalized(&self) -> Requirement {
+            Requirement::new(self.sorted().into_iter().cloned().collect())
+        }
+
+        fn sorted(&self) -> Vec<&(RequirementOperator, Version)> {
+            let mut requirements: Vec<_> = self.requirements.iter().collect();
+            requirements.sort_by(|(a_op, a), (b_op, b)| {
+                a_op.cmp(b_op).then_with(|| a.as_str().cmp(b.as_str()))
+            });
+            requirements
+        }
+
+        /// Matches `Gem::Requirement#none?`: true for the default `>= 0`.
+        pub fn is_none(&self) -> bool {
+            match self.requirements.as_slice() {
+                [(RequirementOperator::GreaterThanOrEqual, version)] => {
+                    !version.is_prerelease() && version.release_numbers().iter().all(|&n| n == 0)
+                }
+                _ => false,
+            }
+        }
+
+        /// Writes the `!ruby/object:Gem::Requirement` mapping, assuming the
+        /// caller has already written the key it belongs to.
+        fn write_ruby_yaml(&self, yaml: &mut String) {
+            yaml.push_str("!ruby/object:Gem::Requirement\n");
+            yaml.push_str("  requirements:\n");
+            for (op, version) in &self.requirements {
+                yaml.push_str(&format!("  - - \"{op}\"\n"));
+                yaml.push_str("    - !ruby/object:Gem::Version\n");
+                yaml.push_str(&format!(
+                    "      version: {}\n",
+                    psych_scalar(version.as_str())
+                ));
+            }
+        }
+
+        /// Returns the pinned version when the requirement is a single `= x`.
+        pub fn as_exact(&self) -> Option<&Version> {
+            match self.requirements.as_slice() {
+                [(RequirementOperator::Equal, version)] => Some(version),
+                _ => None,
+            }
+        }
+
+        /// Matches `Gem::Requirement#satisfied_by?`: every constraint must
+        /// hold. Fails with `Error::Requirement` on an unknown operator,
+        /// which has no meaning to check against.
+        pub fn satisfied_by(&self, version: &Version) -> Result<bool, Error> {
+            for (op, requirement) in &self.requirements {
+                let satisfied = match op {
+                    RequirementOperator::Equal => version == requirement,
+                    RequirementOperator::NotEqual => version != requirement,
+                    RequirementOperator::GreaterThan => version > requirement,
+                    RequirementOperator::GreaterThanOrEqual => version >= requirement,
+                    RequirementOperator::LessThan => version < requirement,
+                    RequirementOperator::LessThanOrEqual => version <= requirement,
+                    RequirementOperator::Tilde => {
+                        version >= requirement && version.release() < requirement.bump()
+                    }
+                    RequirementOperator::Unknown(_) => {
+                        return Err(Error::Requirement(format!(
+                            "{op} {} has an unknown operator",
+                            requirement.as_str()
+                        )));
+                    }
+                };
+                if !satisfied {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+
+        /// Matches `Gem::Requirement#prerelease?`: RubyGems only installs a
+        /// prerelease when some constraint names one.
+        pub fn allows_prerelease(&self) -> bool {
+            self.requirements
+                .iter()
+                .any(|(_, version)| version.is_prerelease())
+        }
+
+        /// Like `satisfied_by`, but unless `allow_pre` is set, prereleases
+        /// are rejected when the requirement doesn't mention one.
+        pub fn satisfied_by_with_policy(
+            &self,
+            version: &Version,
+            allow_pre: bool,
+        ) -> Result<bool, Error> {
+            if version.is_prerelease() && !allow_pre && !self.allows_prerelease() {
+                return Ok(false);
+            }
+            self.satisfied_by(version)
+        }
+
+        /// Reduces the constraints to the tightest lower and upper bound,
+        /// plus the `!=` exclusions between them, so `>= 1.0, >= 0.5, < 3.0,
+        /// < 2.0` becomes `>= 1.0, < 2.0`. A `~>` is first expanded to its
+        /// bounds, `~> 1.2` to `>= 1.2, < 2.A`, so that it folds with the
+        /// rest while still excluding the prereleases of its bump. Bounds
+        /// that cross yield `Requirement::unsatisfiable`.
+        pub fn simplify(&self) -> Requirement {
+            use RequirementOperator::*;
+
+            let expanded: Vec<_> = self
+                .requirements
+                .iter()
+                .flat_map(|(op, version)| match op {
+                    Tilde => vec![
+                        (GreaterThanOrEqual, version.clone()),
+                        (LessThan, version.bump_prerelease()),
+                    ],
+                    _ => vec![(op.clone(), version.clone())],
+                })
+                .collect();
+
+            let mut lower: Option<(&Version, bool)> = None;
+            let mut upper: Option<(&Version, bool)> = None;
+            let mut excluded: Vec<&Version> = Vec::new();
+            let mut unknown = Vec::new();
+            for (op, version) in &expanded {
+                let (lower_bound, upper_bound) = match op {
+                    Equal => (Some(true), Some(true)),
+                    GreaterThan => (Some(false), None),
+                    GreaterThanOrEqual => (Some(true), None),
+                    LessThan => (None, Some(false)),
+                    LessThanOrEqual => (None, Some(true)),
+                    NotEqual => {
+                        excluded.push(version);
+                        (None, None)
+                    }
+                    Tilde | Unknown(_) => {
+                        unknown.push((op.clone(), version.clone()));
+                        (None, None)
+                    }
+                };
+                if let Some(inclusive) = lower_bound
+                    && lower.is_none_or(|(current, current_inclusive)| {
+                        version > current || (version == current && current_inclusive && !inclusive)
+                    })
+                {
+                    lower = Some((version, inclusive));
+                }
+                if let Some(inclusive) = upper_bound
+                    && upper.is_none_or(|(current, current_inclusive)| {
+                        version < current || (version == current && current_inclusive && !inclusive)
+                    })
+                {
+                    upper = Some((version, inclusive));
+                }
+            }
+
+            if let (Some((low, inclusive)), Some((high, high_inclusive))) = (lower, upper)
+                && (low > high || (low == high && !(inclusive && high_inclusive)))
+            {
+                return Requirement::unsatisfiable();
+            }
+
+            let mut requirements = Vec::new();
+            match (lower, upper) {
+                (Some((low, true)), Some((high, true))) if low == high => {
+                    if excluded.contains(&low) {
+                        return Requirement::unsatisfiable();
+                    }
+                    requirements.push((Equal, low.clone()));
+                }
+                _ => {
+                    if let Some((low, inclusive)) = lower {
+                        requirements.push((
+                            if inclusive {
+                                GreaterThanOrEqual
+                            } else {
+                                GreaterThan
+                            },
+                            low.clone(),
+                        ));
+                    }
+                    if let Some((high, inclusive)) = upper {
+                        requirements.push((
+                            if inclusive { LessThanOrEqual } else { LessThan },
+                            high.clone(),
+                        ));
+                    }
+                }
+            }
+
+            let bounds = Requirement::new(requirements.clone());
+            excluded.sort();
+            excluded.dedup();
+            for version in excluded {
+                // The bounds only have known operators, so this can't fail.
+                if bounds.satisfied_by(version).unwrap_or(false) {
+                    requirements.push((NotEqual, version.clone()));
+                }
+            }
+            requirements.extend(unknown);
+            if requirements.is_empty() {
+                requirements.push((
+                    GreaterThanOrEqual,
+                    Version::from_segments(vec![VersionSegment::Number(0)]),
+                ));
+            }
+            Requirement::new(requirements)
+        }
+
+        /// Parses each clause on its own and combines them, for the form
+        /// Gemfiles use: `gem "x", ">= 1.0", "< 2.0"`. No clauses at all is
+        /// `>= 0`.
+        pub fn parse_multiple(clauses: &[&str]) -> Result<Requirement, Error> {
+            if clauses.is_empty() {
+                return Ok(Requirement::default());
+            }
+            let requirements = clauses
+                .iter()
+                .map(|clause| parse_requirement_clause(clause))
+                .collect::<Result<_, _>>()?;
+            Ok(Requirement::new(requirements))
+        }
+
+        /// The requirement `simplify` returns when nothing can satisfy the
+        /// constraints: `> 0, < 0`.
+        pub fn unsatisfiable() -> Requirement {
+            let zero = Version::from_segments(vec![VersionSegment::Number(0)]);
+            Requirement::new(vec![
+                (RequirementOperator::GreaterThan, zero.clone()),
+                (RequirementOperator::LessThan, zero),
+            ])
+        }
+
+        pub fn is_unsatisfiable(&self) -> bool {
+            *self == Requirement::unsatisfiable()
+        }
+
+        /// Whether some version could satisfy every constraint, going by
+        /// `simplify`.
+        pub fn is_satisfiable(&self) -> bool {
+            !self.simplify().is_unsatisfiable()
+        }
+
+        /// Returns the complement as a union: a version fails `self` exactly
+        /// when it satisfies one of the returned requirements, so the
+        /// negation of `>= 1.0, < 2.0` is `[< 1.0, >= 2.0]`. Negating `= x`
+        /// yields a single `!= x`. A `~>` is first expanded to its `>=` and
+        /// `<` bounds, so `~> 1.2` negates to `[< 1.2, >= 2.A]`, which takes
+        /// in the prereleases of its bump that `~>` rejects. Fails with
+        /// `Error::Requirement` on an unknown operator, as `satisfied_by`
+        /// does, since there's no telling what it would exclude.
+        pub fn negate(&self) -> Result<Vec<Requirement>, Error> {
+            use RequirementOperator::*;
+
+            let mut negated = Vec::new();
+            for (op, version) in &self.requirements {
+                let mut push = |op, version: &Version| {
+                    let requirement = Requirement::new(vec![(op, version.clone())]);
+                    if !negated.contains(&requirement) {
+                        negated.push(requirement);
+                    }
+                };
+                match op {
+                    Equal => push(NotEqual, version),
+                    NotEqual => push(Equal, version),
+                    GreaterThan => push(LessThanOrEqual, version),
+                    GreaterThanOrEqual => push(LessThan, version),
+                    LessThan => push(GreaterThanOrEqual, version),
+                    LessThanOrEqual => push(GreaterThan, version),
+                    Tilde => {
+                        push(LessThan, version);
+                        push(GreaterThanOrEqual, &version.bump_prerelease());
+                    }
+                    Unknown(_) => {
+                        return Err(Error::Requirement(format!(
+                            "{op} {} has an unknown operator",
+                            version.as_str()
+                        )));
+                    }
+                }
+            }
+            Ok(negated)
+        }
+
+        /// Matches `Gem::Version#approximate_recommendation`, e.g. `3.4.1`
+        /// yields `~> 3.4` and `1.2.3.rc1` yields `~> 1.2.a`.
+        pub fn for_approximate(version: &Version) -> Requirement {
+            let mut segments = version.release_numbers();
+            segments.truncate(2);
+            segments.resize(2, 0);
+
+            let mut segments: Vec<VersionSegment> =
+                segments.into_iter().map(VersionSegment::Number).collect();
+            if version.is_prerelease() {
+                segments.push(VersionSegment::String("a".to_string()));
+            }
+
+            Requirement::pessimistic(Version::from_segments(segments))
+        }
+
+        /// Translates the requirement into `semver` syntax, expanding `~>` into
+        /// a `>=, <` pair.
+        ///
+        /// This is lossy: `semver` only matches a prerelease against
+        /// comparators on the same `major.minor.patch`, whereas RubyGems
+        /// orders prereleases anywhere in the range. Requirements that can't be
+        /// expressed at all are errors, namely `!=`, versions with more than
+        /// three release segments, and string segments that aren't valid
+        /// SemVer prerelease identifiers.
+        pub fn to_semver_range(&self) -> Result<semver::VersionReq, Error> {
+            let mut comparators = Vec::new();
+            for (op, version) in &self.requirements {
+                let op = match op {
+                    RequirementOperator::Equal => semver::Op::Exact,
+                    RequirementOperator::GreaterThan => semver::Op::Greater,
+                    RequirementOperator::GreaterThanOrEqual => semver::Op::GreaterEq,
+                    RequirementOperator::LessThan => semver::Op::Less,
+                    RequirementOperator::LessThanOrEqual => semver::Op::LessEq,
+                    RequirementOperator::Tilde => {
+                        comparators.push(semver_comparator(semver::Op::GreaterEq, version)?);
+                        comparators.push(semver_comparator(semver::Op::Less, &version.bump())?);
+                        continue;
+                    }
+                    RequirementOperator::NotEqual | RequirementOperator::Unknown(_) => {
+                        return Err(Error::Requirement(format!(
+                            "{op} {} has no SemVer equivalent",
+                            version.as_str()
+                        )));
+                    }
+                };
+                comparators.push(semver_comparator(op, version)?);
+            }
+            Ok(semver::VersionReq { comparators })
+        }
+    }
+
+    fn semver_comparator(op: semver::Op, version: &Version) -> Result<semver::Comparator, Error> {
+        let release = version.release_numbers();
+        let [major, minor, patch] = match release.as_slice() {
+            [major] => [*major, 0, 0],
+            [major, minor] => [*major, *minor, 0],
+            [major, minor, patch] => [*major, *minor, *patch],
+            _ => {
+                return Err(Error::Requirement(format!(
+                    "{} does not have between one and three release segments",
+                    version.as_str()
+                )));
+            }
+        };
+        let pre = version
+            .prerelease_segments()
+            .iter()
+            .map(|segment| match segment {
+                VersionSegment::Number(number) => number.to_string(),
+                VersionSegment::String(string) => string.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        let pre = semver::Prerelease::new(&pre).map_err(|e| {
+            Error::Requirement(format!(
+                "{} has an invalid SemVer prerelease: {e}",
+                version.as_str()
+            ))
+        })?;
+        Ok(semver::Comparator {
+            op,
+            major,
+            minor: Some(minor),
+            patch: Some(patch),
+            pre,
+        })
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    pub enum RequirementOperator {
+        #[serde(rename = "=")]
+        Equal,
+        #[serde(rename = ">")]
+        GreaterThan,
+        #[serde(rename = ">=")]
+        GreaterThanOrEqual,
+        #[serde(rename = "<")]
+        LessThan,
+        #[serde(rename = "<=")]
+        LessThanOrEqual,
+        #[serde(rename = "!=")]
+        NotEqual,
+        #[serde(rename = "~>")]
+        Tilde,
+        /// An operator RubyGems doesn't define, kept as written so that it
+        /// serializes back unchanged.
+        #[serde(untagged)]
+        Unknown(String),
+    }
+
+    impl Display for RequirementOperator {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(match self {
+                RequirementOperator::Equal => "=",
+                RequirementOperator::GreaterThan => ">",
+                RequirementOperator::GreaterThanOrEqual => ">=",
+                RequirementOperator::LessThan => "<",
+                RequirementOperator::LessThanOrEqual => "<=",
+                RequirementOperator::NotEqual => "!=",
+                RequirementOperator::Tilde => "~>",
+                RequirementOperator::Unknown(op) => op,
+            })
+        }
+    }
+
+    impl Display for Requirement {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for (idx, (op, version)) in self.requirements.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{op} {version}")?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Parses comma-separated clauses, such as `>= 1.0, < 2.0`. A version
+    /// without an operator is pinned with `=`, as `Gem::Requirement.parse`
+    /// does.
+    impl FromStr for Requirement {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Requirement::parse_multiple(&s.split(',').collect::<Vec<_>>())
+        }
+    }
+
+    /// Parses one `op version` clause.
+    fn parse_requirement_clause(clause: &str) -> Result<(RequirementOperator, Version), Error> {
+        use RequirementOperator::*;
+
+        let clause = clause.trim();
+        // Two-character operators first, so `>=` isn't read as `>`.
+        let (op, version) = [
+            (">=", GreaterThanOrEqual),
+            ("<=", LessThanOrEqual),
+            ("!=", NotEqual),
+            ("~>", Tilde),
+            ("=", Equal),
+            (">", GreaterThan),
+            ("<", LessThan),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| clause.strip_prefix(prefix).map(|rest| (op, rest)))
+        .unwrap_or((Equal, clause));
+        let version = Version::from_str(version)
+            .map_err(|e| Error::Requirement(format!("{clause:?}: {e}")))?;
+        Ok((op, version))
+    }
+
+    // enum PackageEntry {
+    //     Metadata,
+    //     Checksums(HashMap<String, String>),
+    //     Signature(String, String),
+    // }
+
+    /// Decompresses the data member, which is `data.tar.gz` for everything
+    /// but experimental `data.tar.zst` builds.
+    pub enum DataReader<R: BufRead> {
+        Gzip(GzDecoder<R>),
+        Zstd(zstd::Decoder<'static, R>),
+    }
+
+    impl<R: BufRead> Read for DataReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self {
+                DataReader::Gzip(reader) => reader.read(buf),
+                DataReader::Zstd(reader) => reader.read(buf),
+            }
+        }
+    }
+
+    type DataMember<'a, R> = DataReader<BufReader<Entry<'a, R>>>;
+    type DataArchive<'a, R> = Archive<DataMember<'a, R>>;
+
+    #[derive()]
+    pub struct Package<R>
+    where
+        R: Read + Seek,
+    {
+        archive: Archive<R>,
+        max_metadata_size: u64,
+        max_entry_size: u64,
+        buffer_capacity: usize,
+        specification: Option<Specification>,
+    }
+
+    /// The names the metadata member goes by, in the order they're looked for.
+    const METADATA_MEMBERS: &[&str] = &["metadata.gz", "metadata", "metadata.zst"];
+
+    /// The default for `Package::with_max_metadata_size`. Real gemspecs are
+    /// a few kilobytes, so anything this large is a decompression bomb.
+    pub const DEFAULT_MAX_METADATA_SIZE: u64 = 4 << 20;
+
+    /// The default for `Package::with_max_entry_size`.
+    pub const DEFAULT_MAX_ENTRY_SIZE: u64 = 1 << 30;
+
+    /// The default for `Package::with_buffer_capacity`, the same as
+    /// `BufReader::new` uses.
+    pub const DEFAULT_BUFFER_CAPACITY: usize = 8 << 10;
+
+    /// The digests recorded in `checksums.yaml.gz`, keyed by member name.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+    pub struct Checksums {
+        #[serde(rename = "SHA256", default)]
+        pub sha256: BTreeMap<String, String>,
+        #[serde(rename = "SHA512", default)]
+        pub sha512: BTreeMap<String, String>,
+    }
+
+    /// Decompresses a metadata member, which is `metadata.gz` for everything
+    /// but experimental `metadata.zst` builds. With `truncated`, a read
+    /// error after some YAML has come out is ignored, keeping what was read.
+    fn decompress_metadata<E: Read>(
+        entry: Entry<'_, E>,
+        capacity: usize,
+        limit: u64,
+        truncated: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let member = entry.path()?.display().to_string();
+        let zstd = member.ends_with(".zst");
+
+        // Some hand-built gems ship plain YAML, even under `metadata.gz`.
+        let mut reader = BufReader::with_capacity(capacity, entry);
+        let reader: Box<dyn Read + '_> = if zstd {
+            Box::new(zstd::Decoder::with_buffer(reader)?)
+        } else if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        // Read one byte past the limit to tell a bomb from an exact fit.
+        let mut contents = Vec::new();
+        let read = reader
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut contents);
+
+        if let Err(e) = read
+            && (!truncated || contents.is_empty())
+        {
+            return Err(e.into());
+        }
+        if contents.len() as u64 > limit {
+            return Err(Error::DecompressedTooLarge { member, limit });
+        }
+        Ok(contents)
+    }
+
+    /// Wraps the data member in its decompressor, along with its
+    /// uncompressed size if the member records one.
+    fn data_reader<'a, E: Read>(
+        entry: Entry<'a, E>,
+        capacity: usize,
+    ) -> Result<(DataMember<'a, E>, Option<u64>), Error> {
+        if entry.path()?.extension().is_some_and(|ext| ext == "zst") {
+            let mut reader = BufReader::with_capacity(capacity, entry);
+            let total = zstd::zstd_safe::get_frame_content_size(reader.fill_buf()?)
+                .ok()
+                .flatten();
+            Ok((DataReader::Zstd(zstd::Decoder::with_buffer(reader)?), total))
+        } else {
+            Ok((
+                DataReader::Gzip(GzDecoder::new(BufReader::with_capacity(capacity, entry))),
+                None,
+            ))
+        }
+    }
+
+    /// Returns the first top-level member named one of `names`, failing
+    /// rather than panicking on a corrupt header or path.
+    fn find_member<'a, E: Read>(
+        entries: tar::Entries<'a, E>,
+        names: &[&str],
+    ) -> Result<Option<Entry<'a, E>>, Error> {
+        for entry in entries {
+            let entry = entry?;
+            if entry
+                .path()?
+                .to_str()
+                .is_some_and(|path| names.contains(&path))
+            {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Rejects entries by the size in their header, before they're read.
+    fn check_entry_size<E: Read>(entry: &Entry<E>, limit: u64) -> Result<(), Error> {
+        if entry.size() > limit {
+            return Err(Error::DecompressedTooLarge {
+                member: entry.path()?.display().to_string(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// The path of a `data.tar.gz` entry, normalized with
+    /// `normalize_entry_path`.
+    pub fn entry_path<E: Read>(entry: &Entry<E>) -> Result<String, Error> {
+        normalize_entry_path(&entry.path()?.to_string_lossy())
+    }
+
+    /// Strips leading `./` and repeated or trailing separators from an entry
+    /// path, so `./lib//foo.rb` is listed like the spec's `lib/foo.rb`.
+    /// Absolute paths and `..` components, which would escape the directory
+    /// the gem is installed into, are rejected.
+    pub fn normalize_entry_path(path: &str) -> Result<String, Error> {
+        if path.starts_with('/') {
+            return Err(Error::UnsafePath(path.to_string()));
+        }
+        let mut components = Vec::new();
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => return Err(Error::UnsafePath(path.to_string())),
+                component => components.push(component),
+            }
+        }
+        Ok(components.join("/"))
+    }
+
+    impl Package<Cursor<Vec<u8>>> {
+        /// Reads a gem that is already in memory, e.g. one just downloaded.
+        pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+            Package::new(Cursor::new(bytes.into()))
+        }
+    }
+
+    impl Package<File> {
+        pub fn from_path(path: &Path) -> Result<Self, Error> {
+            Ok(Package::new(File::open(path)?))
+        }
+    }
+
+    impl<R> Package<R>
+    where
+        R: Read + Seek,
+    {
+        pub fn new(io: R) -> Package<R> {
+            let archive = tar::Archive::new(io);
+            Package {
+                archive,
+                max_metadata_size: DEFAULT_MAX_METADATA_SIZE,
+                max_entry_size: DEFAULT_MAX_ENTRY_SIZE,
+                buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+                specification: None,
+            }
+        }
+
+        /// Caps how large the decompressed metadata may be, failing with
+        /// `Error::DecompressedTooLarge` instead of reading past it.
+        pub fn with_max_metadata_size(mut self, limit: u64) -> Self {
+            self.max_metadata_size = limit;
+            self
+        }
+
+        /// Caps the size of each file in the data tarball, as read by
+        /// `each_entry` and `read_file`.
+        pub fn with_max_entry_size(mut self, limit: u64) -> Self {
+            self.max_entry_size = limit;
+            self
+        }
+
+        /// Sets the capacity of the buffers the compressed metadata and data
+        /// members are read through. Larger buffers mean fewer reads from
+        /// `R`, which helps when scanning many gems. Capacities under 64
+        /// bytes are rounded up, leaving room to sniff compression headers.
+        pub fn with_buffer_capacity(mut self, bytes: usize) -> Self {
+            self.buffer_capacity = bytes.max(64);
+            self
+        }
+
+        /// Returns the decompressed YAML from `metadata.gz`, without parsing
+        /// it into a `Specification`.
+        pub fn metadata_bytes(&mut self) -> Result<String, Error> {
+            String::from_utf8(self.read_metadata(false)?)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+        }
+
+        /// Finds and decompresses the metadata member, as
+        /// `decompress_metadata` does.
+        fn read_metadata(&mut self, truncated: bool) -> Result<Vec<u8>, Error> {
+            let contents = (|| {
+                let entry = find_member(self.archive.entries_with_seek()?, METADATA_MEMBERS)?
+                    .ok_or(Error::MissingMetadata)?;
+                decompress_metadata(
+                    entry,
+                    self.buffer_capacity,
+                    self.max_metadata_size,
+                    truncated,
+                )
+            })();
+            self.archive.reset()?;
+            contents
+        }
+
+        /// Salvages what it can from metadata that's truncated or otherwise
+        /// damaged, for when `specification` fails.
+        pub fn partial_specification(&mut self) -> Result<PartialSpecification, Error> {
+            let contents = self.read_metadata(true)?;
+            Ok(PartialSpecification::from_yaml(&String::from_utf8_lossy(
+                &contents,
+            )))
+        }
+
+        /// Lists the names of the top-level members, such as `metadata.gz`,
+        /// `data.tar.gz` and `checksums.yaml.gz`, in archive order.
+        pub fn members(&mut self) -> Result<Vec<String>, Error> {
+            let members = (|| {
+                let mut members = Vec::new();
+                for entry in self.archive.entries_with_seek()? {
+                    members.push(entry?.path()?.display().to_string());
+                }
+                Ok(members)
+            })();
+            self.archive.reset()?;
+            members
+        }
+
+        /// Returns the raw bytes of the top-level member `name`, without
+        /// decompressing them, or `None` if there's no such member.
+        pub fn member_bytes(&mut self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+            let contents = (|| {
+                for entry in self.archive.entries_with_seek()? {
+                    let mut entry = entry?;
+                    if entry.path()?.to_str() == Some(name) {
+                        check_entry_size(&entry, self.max_entry_size)?;
+                        let mut bytes = Vec::new();
+                        entry.read_to_end(&mut bytes)?;
+                        return Ok(Some(bytes));
+                    }
+                }
+                Ok(None)
+            })();
+            self.archive.reset()?;
+            contents
+        }
+
+        /// Parses `checksums.yaml.gz`, without checking it against the other
+        /// members. Gems built before RubyGems recorded checksums have empty
+        /// `Checksums`.
+        pub fn recorded_checksums(&mut self) -> Result<Checksums, Error> {
+            let member = "checksums.yaml.gz";
+            let Some(bytes) = self.member_bytes(member)? else {
+                return Ok(Checksums::default());
+            };
+            let mut yaml = Vec::new();
+            GzDecoder::new(bytes.as_slice())
+                .take(self.max_metadata_size.saturating_add(1))
+                .read_to_end(&mut yaml)?;
+            if yaml.len() as u64 > self.max_metadata_size {
+                return Err(Error::DecompressedTooLarge {
+                    member: member.to_string(),
+                    limit: self.max_metadata_size,
+                });
+            }
+            Ok(serde_yaml::from_slice(&yaml)?)
+        }
+
+        /// Recomputes each digest in `recorded_checksums`, failing with
+        /// `Error::ChecksumMismatch` for the first member that differs. A
+        /// recorded member that's missing from the gem is a mismatch too.
+        pub fn verify_checksums(&mut self) -> Result<(), Error> {
+            let checksums = self.recorded_checksums()?;
+            let digests = [
+                (
+                    &checksums.sha256,
+                    hex_digest::<sha2::Sha256> as fn(&[u8]) -> String,
+                ),
+                (&checksums.sha512, hex_digest::<sha2::Sha512>),
+            ];
+            for (recorded, digest) in digests {
+                for (member, expected) in recorded {
+                    let actual = match self.member_bytes(member)? {
+                        Some(bytes) => digest(&bytes),
+                        None => "nothing, as the member is missing".to_string(),
+                    };
+                    if !actual.eq_ignore_ascii_case(expected) {
+                        return Err(Error::ChecksumMismatch {
+                            member: member.clone(),
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        /// Parses `metadata.gz` on the first call and returns copies of that
+        /// afterwards. Failures aren't cached, so a later call tries again.
+        pub fn specification(&mut self) -> Result<Specification, Error> {
+            if let Some(specification) = &self.specification {
+                return Ok(specification.clone());
+            }
+            let contents = self.metadata_bytes()?;
+            let specification = Specification::from_yaml(&contents)?;
+            self.specification = Some(specification.clone());
+            Ok(specification)
+        }
+
+        pub fn each_entry(
+            &mut self,
+            f: impl FnMut(&mut Entry<DataReader<BufReader<Entry<R>>>>) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            self.each_entry_with_progress(|_, _| {}, f)
+        }
+
+        /// Like `each_entry`, but only calls `f` for entries whose path,
+        /// normalized as `entry_path` does, matches `pattern`. The rest are
+        /// skipped without their contents being read, though a compressed
+        /// data tarball still has to be decompressed past them.
+        pub fn each_entry_matching(
+            &mut self,
+            pattern: &GlobSet,
+            mut f: impl FnMut(&mut Entry<DataReader<BufReader<Entry<R>>>>) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            self.each_entry(|entry| {
+                if pattern.is_match(entry_path(entry)?) {
+                    f(entry)?;
+                }
+                Ok(())
+            })
+        }
+
+        /// Like `each_entry`, but calls `on_progress` after each entry with
+        /// the uncompressed bytes processed so far and the uncompressed size
+        /// of the data tarball. The size is only known for zstd members that
+        /// record it in their frame header, so it's `None` for `data.tar.gz`.
+        pub fn each_entry_with_progress(
+            &mut self,
+            mut on_progress: impl FnMut(u64, Option<u64>),
+            mut f: impl FnMut(&mut Entry<DataReader<BufReader<Entry<R>>>>) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            let max_entry_size = self.max_entry_size;
+            let visited = (|| {
+                let (mut archive, total) = self.data_archive()?;
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    entry_path(&entry)?;
+                    check_entry_size(&entry, max_entry_size)?;
+                    f(&mut entry)?;
+                    // Entries are padded to 512-byte blocks, after their header.
+                    let end = entry.raw_file_position() + entry.size().next_multiple_of(512);
+                    on_progress(end, total);
+                }
+                Ok(())
+            })();
+            // Reset even if `f` failed, so the package can be read again.
+            self.archive.reset()?;
+            visited
+        }
+
+        /// Returns the contents of `path` in `data.tar.gz`, skipping over the
+        /// bodies of the entries before it and stopping once it's found.
+        /// `path` is normalized like entry paths, so `./lib//foo.rb` finds
+        /// `lib/foo.rb`, and fails with `Error::UnsafePath` where they would.
+        pub fn read_file(&mut self, path: &str) -> Result<Option<Vec<u8>>, Error> {
+            let path = normalize_entry_path(path)?;
+            let max_entry_size = self.max_entry_size;
+            let contents = (|| {
+                for entry in self.data_archive()?.0.entries()? {
+                    let mut entry = entry?;
+                    if entry_path(&entry)? == path {
+                        check_entry_size(&entry, max_entry_size)?;
+                        let mut bytes = Vec::new();
+                        entry.read_to_end(&mut bytes)?;
+                        return Ok(Some(bytes));
+                    }
+                }
+                Ok(None)
+            })();
+            self.archive.reset()?;
+            contents
+        }
+
+        /// Opens the data tarball, along with its uncompressed size if the
+        /// member records one.
+        fn data_archive(&mut self) -> Result<(DataArchive<'_, R>, Option<u64>), Error> {
+            let capacity = self.buffer_capacity;
+            let entry = find_member(
+                self.archive.entries_with_seek()?,
+                &["data.tar.gz", "data.tar.zst"],
+            )?
+            .ok_or(Error::MissingData)?;
+            let (reader, total) = data_reader(entry, capacity)?;
+            Ok((tar::Archive::new(reader), total))
+        }
+
+        /// Returns the hex SHA256 of the compressed `data.tar.gz` member, as
+        /// recorded in `checksums.yaml.gz`.
+        pub fn data_tarball_sha256(&mut self) -> Result<String, Error> {
+            let sha256 = (|| {
+                let entry = find_member(
+                    self.archive.entries_with_seek()?,
+                    &["data.tar.gz", "data.tar.zst"],
+                )?
+                .ok_or(Error::MissingData)?;
+                sha256_file(entry)
+            })();
+            self.archive.reset()?;
+            sha256
+        }
+
+        /// Lists the paths of the files and symlinks in `data.tar.gz`.
+        pub fn files(&mut self) -> Result<Vec<String>, Error> {
+            let mut files = Vec::new();
+            self.each_entry(|entry| {
+                if !entry.header().entry_type().is_dir() {
+                    files.push(entry_path(entry)?);
+                }
+                Ok(())
+            })?;
+            Ok(files)
+        }
+
+        /// Parses the specification and lists the files and symlinks in
+        /// `data.tar.gz` in a single pass over the outer archive, rather than
+        /// the two that `specification` and `files` take. The specification
+        /// is cached as `specification` would cache it.
+        pub fn spec_and_files(&mut self) -> Result<(Specification, Vec<FileInfo>), Error> {
+            let capacity = self.buffer_capacity;
+            let (max_metadata_size, max_entry_size) = (self.max_metadata_size, self.max_entry_size);
+            let (mut metadata, mut files) = (None, None);
+            let read = (|| {
+                for entry in self.archive.entries_with_seek()? {
+                    let entry = entry?;
+                    let member = entry.path()?.to_str().map(str::to_owned);
+                    match member.as_deref() {
+                        Some("metadata.gz" | "metadata" | "metadata.zst") if metadata.is_none() => {
+                            metadata = Some(decompress_metadata(
+                                entry,
+                                capacity,
+                                max_metadata_size,
+                                false,
+                            )?);
+                        }
+                        Some("data.tar.gz" | "data.tar.zst") if files.is_none() => {
+                            let mut list = Vec::new();
+                            for entry in Archive::new(data_reader(entry, capacity)?.0).entries()? {
+                                let entry = entry?;
+                                let path = entry_path(&entry)?;
+                                check_entry_size(&entry, max_entry_size)?;
+                                let header = entry.header();
+                                if header.entry_type().is_dir() {
+                                    continue;
+                                }
+                                list.push(FileInfo {
+                                    path,
+                                    size: entry.size(),
+                                    mode: header.mode()?,
+                                    mtime: header.mtime()?,
+                                    link_name: entry
+                                        .link_name()?
+                                        .map(|link| link.display().to_string()),
+                                });
+                            }
+                            files = Some(list);
+                        }
+                        _ => {}
+                    }
+                    if metadata.is_some() && files.is_some() {
+                        break;
+                    }
+                }
+                Ok::<_, Error>(())
+            })();
+            self.archive.reset()?;
+            read?;
+
+            let metadata = String::from_utf8(metadata.ok_or(Error::MissingMetadata)?)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let specification = Specification::from_yaml(&metadata)?;
+            self.specification = Some(specification.clone());
+            Ok((specification, files.ok_or(Error::MissingData)?))
+        }
+
+        /// Hashes every file in `data.tar.gz` and compares it against
+        /// `expected`, a SHA256 per path such as an earlier run recorded.
+        /// Returns the sorted paths that differ, that `expected` lists but
+        /// the gem lacks, or that the gem has but `expected` doesn't.
+        pub fn verify_files(
+            &mut self,
+            expected: &HashMap<String, [u8; 32]>,
+        ) -> Result<Vec<String>, Error> {
+            let mut mismatched = Vec::new();
+            let mut seen = BTreeSet::new();
+            self.each_entry(|entry| {
+                if entry.header().entry_type().is_dir() {
+                    return Ok(());
+                }
+                let path = entry_path(entry)?;
+                let mut hasher = sha2::Sha256::new();
+                std::io::copy(entry, &mut hasher)?;
+                if expected.get(&path) != Some(&hasher.finalize().into()) {
+                    mismatched.push(path.clone());
+                }
+                seen.insert(path);
+                Ok(())
+            })?;
+
+            mismatched.extend(
+                expected
+                    .keys()
+                    .filter(|path| !seen.contains(path.as_str()))
+                    .cloned(),
+            );
+            mismatched.sort();
+            Ok(mismatched)
+        }
+
+        /// Sums the sizes in the `data.tar.gz` headers, without reading the
+        /// files, to give the footprint of the gem once it's extracted.
+        pub fn installed_size(&mut self) -> Result<u64, Error> {
+            let mut size = 0;
+            self.each_entry(|entry| {
+                size += entry.header().size()?;
+                Ok(())
+            })?;
+            Ok(size)
+        }
+
+        /// The sorted paths of files that appear more than once in
+        /// `data.tar.gz`, comparing paths as `entry_path` normalizes them.
+        /// Tar allows this, and extracting such a gem keeps the last copy.
+        pub fn duplicate_files(&mut self) -> Result<Vec<String>, Error> {
+            let mut seen = BTreeSet::new();
+            let mut duplicates = BTreeSet::new();
+            self.each_entry(|entry| {
+                if !entry.header().entry_type().is_dir() {
+                    let path = entry_path(entry)?;
+                    if !seen.insert(path.clone()) {
+                        duplicates.insert(path);
+                    }
+                }
+                Ok(())
+            })?;
+            Ok(duplicates.into_iter().collect())
+        }
+
+        /// Whether `data.tar.gz` has no files, only directories or nothing at
+        /// all, as in placeholder gems pushed to reserve a name. Only the
+        /// data is read, so this works even if the specification doesn't.
+        pub fn is_empty_data(&mut self) -> Result<bool, Error> {
+            let mut empty = true;
+            self.each_entry(|entry| {
+                empty &= entry.header().entry_type().is_dir();
+                Ok(())
+            })?;
+            Ok(empty)
+        }
+
+        /// Returns the mtime shared by every entry in `data.tar.gz`, which is
+        /// how reproducible builds record `SOURCE_DATE_EPOCH`, or `None` if the
+        /// mtimes vary.
+        pub fn source_date_epoch(&mut self) -> Result<Option<u64>, Error> {
+            let mut mtimes = BTreeSet::new();
+            self.each_entry(|entry| {
+                mtimes.insert(entry.header().mtime()?);
+                Ok(())
+            })?;
+            Ok(match mtimes.len() {
+                1 => mtimes.pop_first(),
+                _ => None,
+            })
+        }
+
+        /// Cross-checks `spec.files` against the contents of `data.tar.gz`,
+        /// returning a message for every path that is declared but missing or
+        /// present but undeclared.
+        pub fn validate_manifest(&mut self) -> Result<Vec<String>, Error> {
+            let specification = self.specification()?;
+            let declared: BTreeSet<&str> = specification.files.iter().map(String::as_str).collect();
+            let files = self.files()?;
+            let present: BTreeSet<&str> = files.iter().map(String::as_str).collect();
+
+            let missing = declared
+                .difference(&present)
+                .map(|path| format!("{path} is listed in files but missing from data.tar.gz"));
+            let undeclared = present
+                .difference(&declared)
+                .map(|path| format!("{path} is in data.tar.gz but not listed in files"));
+            Ok(missing.chain(undeclared).collect())
+        }
+
+        /// Verifies the metadata member's `.sig`, such as `metadata.gz.sig`,
+        /// against the leaf certificate of the specification's `cert_chain`,
+        /// and checks that the chain is self-consistent and valid at the
+        /// specification's `date`.
+        pub fn verify_signature(&mut self) -> Result<SignatureStatus, Error> {
+            let specification = self.specification()?;
+
+            let mut members = HashMap::new();
+            let read = (|| {
+                for entry in self.archive.entries_with_seek()? {
+                    let mut entry = entry?;
+                    let Some(path) = entry.path()?.to_str().map(str::to_string) else {
+                        continue;
+                    };
+                    let member = path.strip_suffix(".sig").unwrap_or(&path);
+                    if !METADATA_MEMBERS.contains(&member) || members.contains_key(&path) {
+                        continue;
+                    }
+                    let mut contents = Vec::new();
+                    entry.read_to_end(&mut contents)?;
+                    members.insert(path, contents);
+                }
+                Ok::<_, Error>(())
+            })();
+            self.archive.reset()?;
+            read?;
+
+            let Some((member, signature)) = METADATA_MEMBERS.iter().find_map(|member| {
+                members
+                    .get(&format!("{member}.sig"))
+                    .map(|signature| (*member, signature))
+            }) else {
+                return Ok(SignatureStatus::Unsigned);
+            };
+            let metadata = members.get(member).ok_or(Error::MissingMetadata)?;
+
+            let cert_chain = specification.cert_chain.unwrap_or_default();
+            let chain = match cert_chain
+                .iter()
+                .map(|pem| Certificate::from_pem(pem.as_bytes()))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(chain) if chain.is_empty() => {
+                    return Ok(SignatureStatus::Invalid(format!(
+                        "{member} is signed but cert_chain is empty"
+                    )));
+                }
+                Ok(chain) => chain,
+                Err(err) => {
+                    return Ok(SignatureStatus::Invalid(format!(
+                        "failed to parse cert_chain: {err}"
+                    )));
+                }
+            };
+
+            let status = verify_chain(&chain, &specification.date, member, metadata, signature);
+            Ok(match status {
+                Ok(()) => SignatureStatus::Valid,
+                Err(err) => SignatureStatus::Invalid(format!("{err:#}")),
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum SignatureStatus {
+        /// The metadata member's signature checks out. Only the metadata is
+        /// verified: `data.tar.gz.sig` and `checksums.yaml.gz.sig` are
+        /// ignored, so a tampered data tarball still reports `Valid`.
+        Valid,
+        Unsigned,
+        Invalid(String),
+    }
+
+    /// RubyGems orders `cert_chain` from the root to the signing certificate.
+    fn verify_chain(
+        chain: &[Certificate],
+        date: &DateTime<chrono::Utc>,
+        member: &str,
+        metadata: &[u8],
+        signature: &[u8],
+    ) -> anyhow::Result<()> {
+        let at = std::time::SystemTime::from(*date);
+        for cert in chain {
+            let validity = &cert.tbs_certificate.validity;
+            if at < validity.not_before.to_system_time() || at > validity.not_after.to_system_time()
+            {
+                bail!(
+                    "certificate {} is not valid at {}",
+                    cert.tbs_certificate.subject,
+                    date.to_rfc3339()
+                );
+            }
+        }
+
+        let root = chain.first().context("empty cert_chain")?;
+        if root.tbs_certificate.issuer != root.tbs_certificate.subject {
+            bail!(
+                "root certificate {} is not self-signed",
+                root.tbs_certificate.subject
+            );
+        }
+        verify_certificate(root, root)?;
+        for pair in chain.windows(2) {
+            let (issuer, cert) = (&pair[0], &pair[1]);
+            if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+                bail!(
+                    "certificate {} was not issued by {}",
+                    cert.tbs_certificate.subject,
+                    issuer.tbs_certificate.subject
+                );
+            }
+            verify_certificate(cert, issuer)?;
+        }
+
+        let leaf = public_key(chain.last().unwrap())?;
+        let sha256 = sha2::Sha256::digest(metadata);
+        let sha512 = sha2::Sha512::digest(metadata);
+        leaf.verify(Pkcs1v15Sign::new::<sha2::Sha256>(), &sha256, signature)
+            .or_else(|_| leaf.verify(Pkcs1v15Sign::new::<sha2::Sha512>(), &sha512, signature))
+            .with_context(|| format!("{member}.sig does not match {member}"))
+    }
+
+    fn verify_certificate(cert: &Certificate, issuer: &Certificate) -> anyhow::Result<()> {
+        let tbs = cert.tbs_certificate.to_der()?;
+        let signature = cert
+            .signature
+            .as_bytes()
+            .context("certificate signature has unused bits")?;
+        let key = public_key(issuer)?;
+        let result = match cert.signature_algorithm.oid.to_string().as_str() {
+            "1.2.840.113549.1.1.5" => key.verify(
+                Pkcs1v15Sign::new::<sha1::Sha1>(),
+                &sha1::Sha1::digest(&tbs),
+                signature,
+            ),
+            "1.2.840.113549.1.1.11" => key.verify(
+                Pkcs1v15Sign::new::<sha2::Sha256>(),
+                &sha2::Sha256::digest(&tbs),
+                signature,
+            ),
+            "1.2.840.113549.1.1.12" => key.verify(
+                Pkcs1v15Sign::new::<sha2::Sha384>(),
+                &sha2::Sha384::digest(&tbs),
+                signature,
+            ),
+            "1.2.840.113549.1.1.13" => key.verify(
+                Pkcs1v15Sign::new::<sha2::Sha512>(),
+                &sha2::Sha512::digest(&tbs),
+                signature,
+            ),
+            oid => bail!("unsupported certificate signature algorithm {oid}"),
+        };
+        result.with_context(|| {
+            format!(
+                "certificate {} has an invalid signature",
+                cert.tbs_certificate.subject
+            )
+        })
+    }
+
+    fn public_key(cert: &Certificate) -> anyhow::Result<RsaPublicKey> {
+        let spki = cert.tbs_certificate.subject_public_key_info.to_der()?;
+        RsaPublicKey::from_public_key_der(&spki).with_context(|| {
+            format!(
+                "certificate {} does not have an RSA public key",
+                cert.tbs_certificate.subject
+            )
+        })
+    }
+
+    fn hex_digest<D: Digest>(bytes: &[u8]) -> String {
+        D::digest(bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Returns the lowercase hex SHA256 of an entire `.gem` file, which is
+    /// the `sha` that rubygems.org reports for a version.
+    pub fn sha256_file<R: Read>(mut r: R) -> Result<String, Error> {
+        let mut hasher = sha2::Sha256::new();
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = r.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Runs `f` over every `.gem` in `dir` on a pool of `max_parallel`
+    /// threads, so at most that many packages are open at once. A failing
+    /// gem doesn't stop the others; the failures are returned, sorted by
+    /// path. Only listing `dir` or building the pool fails the whole run.
+    pub fn process_cache_dir<F>(
+        dir: &Path,
+        max_parallel: usize,
+        f: F,
+    ) -> Result<Vec<(PathBuf, anyhow::Error)>, Error>
+    where
+        F: Fn(&Path, &mut Package<File>) -> anyhow::Result<()> + Sync,
+    {
+        let mut paths = Vec::new();
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "gem") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(max_parallel.max(1))
+            .build()?;
+        Ok(pool.install(|| {
+            paths
+                .into_par_iter()
+                .filter_map(|path| {
+                    let result = Package::from_path(&path)
+                        .with_context(|| format!("Failed to open {}", path.display()))
+                        .and_then(|mut package| f(&path, &mut package));
+                    result.err().map(|err| (path, err))
+                })
+                .collect()
+        }))
+    }
+
+    /// A file or symlink in `data.tar.gz`, as listed by
+    /// `Package::spec_and_files`.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct FileInfo {
+        /// The path, normalized as `entry_path` does.
+        pub path: String,
+        pub size: u64,
+        pub mode: u32,
+        pub mtime: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub link_name: Option<String>,
+    }
+
+    #[serde_as]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    pub struct PackageEntry<'a> {
+        pub gem: &'a str,
+        pub version: &'a str,
+        pub platform: &'a str,
+        pub size: u64,
+        pub path: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub link_name: Option<&'a str>,
+        pub mode: u32,
+        #[serde(skip_serializing)]
+        pub uid: u64,
+        #[serde(skip_serializing)]
+        pub gid: u64,
+        pub mtime: u64,
+        #[serde_as(as = "serde_with::hex::Hex")]
+        pub sha256: GenericArray<u8, <sha2::Sha256 as sha2::digest::OutputSizeUser>::OutputSize>,
+        pub magic: String,
+    }
+
+    impl PackageEntry<'_> {
+        /// Whether any of the owner, group or other execute bits are set.
+        pub fn is_executable(&self) -> bool {
+            self.mode & 0o111 != 0
+        }
+
+        pub fn is_symlink(&self) -> bool {
+            self.link_name.is_some()
+        }
+
+        /// Whether `magic` names anything other than a `text/` type.
+        pub fn is_binary(&self) -> bool {
+            !self.magic.starts_with("text/")
+        }
     }
 }
@@ -0,0 +1,25 @@
+#![cfg(feature = "proptest")]
+
+use gemspec_rs::gem::{Requirement, Version, arbitrary::satisfied_by};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn versions_round_trip_through_strings(version in any::<Version>()) {
+        let parsed: Version = version.as_str().parse().unwrap();
+        prop_assert_eq!(parsed.as_str(), version.as_str());
+    }
+
+    #[test]
+    fn requirements_are_satisfiable(requirement in any::<Requirement>()) {
+        prop_assert!(!requirement.simplify().is_unsatisfiable(), "{}", requirement);
+    }
+
+    #[test]
+    fn requirements_hold_for_their_witness(
+        (version, requirement) in any::<Version>()
+            .prop_flat_map(|version| (Just(version.clone()), satisfied_by(version)))
+    ) {
+        prop_assert!(requirement.satisfied_by(&version).unwrap(), "{} {}", requirement, version);
+    }
+}
@@ -0,0 +1,75 @@
+use gemspec_rs::gem::{Error, Package, Specification, builder::GemWriter};
+
+fn spec() -> Specification {
+    Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .license("MIT")
+        .files(["exe/hello-world", "lib/hello_world.rb"])
+        .date("2025-03-01T00:00:00Z".parse().unwrap())
+        .build()
+        .unwrap()
+}
+
+fn build(spec: &Specification) -> Vec<u8> {
+    let mut writer = GemWriter::new();
+    writer
+        .add_file("exe/hello-world", b"#!/usr/bin/env ruby\n", 0o755)
+        .unwrap();
+    writer
+        .add_file("./lib/hello_world.rb", b"puts 'Hello, world!'\n", 0o644)
+        .unwrap();
+    let mut gem = Vec::new();
+    writer.finish(spec, &mut gem).unwrap();
+    gem
+}
+
+#[test]
+fn round_trips_through_package() {
+    let spec = spec();
+    let mut package = Package::from_bytes(build(&spec));
+
+    assert_eq!(
+        package.members().unwrap(),
+        ["metadata.gz", "data.tar.gz", "checksums.yaml.gz"]
+    );
+    assert_eq!(package.specification().unwrap(), spec);
+    assert_eq!(package.files().unwrap(), spec.files);
+    assert_eq!(
+        package.read_file("lib/hello_world.rb").unwrap(),
+        Some(b"puts 'Hello, world!'\n".to_vec())
+    );
+    assert_eq!(package.validate_manifest().unwrap(), Vec::<String>::new());
+    package.verify_checksums().unwrap();
+    let checksums = package.recorded_checksums().unwrap();
+    assert_eq!(checksums.sha256.len(), 2);
+    assert_eq!(checksums.sha512.len(), 2);
+
+    let mut modes = Vec::new();
+    package
+        .each_entry(|entry| {
+            modes.push(entry.header().mode()?);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(modes, [0o755, 0o644]);
+}
+
+#[test]
+fn builds_are_reproducible() {
+    let spec = spec();
+    assert_eq!(build(&spec), build(&spec));
+}
+
+#[test]
+fn rejects_unsafe_paths() {
+    let mut writer = GemWriter::new();
+    for path in ["../evil.rb", "/etc/passwd", "./"] {
+        assert!(matches!(
+            writer.add_file(path, b"", 0o644),
+            Err(Error::UnsafePath(_))
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+use std::{fs::File, path::Path};
+
+use gemspec_rs::gem::{
+    Error,
+    index::{parse_specs, parse_specs_gz},
+};
+
+#[test]
+fn parse_specs_gz_fixture() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/specs.4.8.gz");
+    let specs = parse_specs_gz(File::open(path).unwrap()).unwrap();
+
+    let specs: Vec<_> = specs
+        .iter()
+        .map(|(name, version, platform)| (name.as_str(), version.as_str(), platform.as_str()))
+        .collect();
+    // The later `ruby` platforms are object links to the first.
+    assert_eq!(
+        specs,
+        [
+            ("rake", "13.0", "ruby"),
+            ("rake", "12.3.3", "ruby"),
+            ("nokogiri", "1.16.0", "x86_64-linux"),
+            ("rails", "8.0.0.rc1", "ruby"),
+        ]
+    );
+}
+
+#[test]
+fn parse_specs_rejects_malformed_indexes() {
+    for bytes in [
+        &b"\x04\x09["[..],
+        b"\x04\x08[\x06",
+        b"\x04\x08[\x06[\x06I\"\x09rake\x06:\x06ET",
+        b"\x04\x08{\x00",
+    ] {
+        assert!(
+            matches!(parse_specs(bytes), Err(Error::Marshal(_))),
+            "{bytes:?}"
+        );
+    }
+    assert_eq!(parse_specs(b"\x04\x08[\x00").unwrap(), []);
+}
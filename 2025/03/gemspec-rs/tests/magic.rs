@@ -0,0 +1,50 @@
+use gemspec_rs::gem::magic::{DefaultMagicDetector, ExtensionMagicDetector, MagicDetector};
+
+fn detect(path: &str) -> String {
+    ExtensionMagicDetector.detect(b"", path)
+}
+
+#[test]
+fn extension_detector_knows_gem_file_types() {
+    assert_eq!(detect("lib/hello_world.rb"), "application/x-ruby");
+    assert_eq!(detect("hello-world.gemspec"), "application/x-ruby");
+    assert_eq!(detect("README.md"), "text/markdown");
+    assert_eq!(detect("ext/hello/hello.c"), "text/x-csrc");
+    assert_eq!(detect("lib/hello.so"), "application/x-sharedlib");
+    assert_eq!(detect("data/config.YML"), "application/x-yaml");
+    assert_eq!(detect("Rakefile"), "text/plain");
+}
+
+#[test]
+fn extension_detector_ignores_contents() {
+    assert_eq!(
+        ExtensionMagicDetector.detect(b"\x7fELF", "README.md"),
+        "text/markdown"
+    );
+}
+
+#[test]
+fn extension_detector_falls_back_to_octet_stream() {
+    assert_eq!(detect("data.bin"), "application/octet-stream");
+    assert_eq!(detect("Gemfile.lock"), "application/octet-stream");
+    assert_eq!(detect("bin/hello-world"), "application/octet-stream");
+    assert_eq!(detect(""), "application/octet-stream");
+}
+
+#[cfg(not(feature = "tree_magic"))]
+#[test]
+fn default_detector_uses_extensions_without_tree_magic() {
+    assert_eq!(
+        DefaultMagicDetector::default().detect(b"", "lib/hello_world.rb"),
+        "application/x-ruby"
+    );
+}
+
+#[cfg(feature = "tree_magic")]
+#[test]
+fn default_detector_sniffs_contents_with_tree_magic() {
+    assert_eq!(
+        DefaultMagicDetector::default().detect(b"%PDF-1.4\n", "README.md"),
+        "application/pdf"
+    );
+}
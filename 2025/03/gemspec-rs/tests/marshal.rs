@@ -0,0 +1,123 @@
+use std::io::Read;
+
+use chrono::{TimeZone, Utc};
+use flate2::read::ZlibDecoder;
+use gemspec_rs::gem::{
+    Dependency, DependencyType, Requirement, RequirementOperator, Specification,
+    marshal::{compress_rz, dump_quick_spec, load_quick_spec},
+};
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+fn spec(platform: &str) -> Specification {
+    Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .platform(platform.parse().unwrap())
+        .date(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap())
+        .add_dependency(Dependency::new(
+            "rake".to_string(),
+            Requirement::new(vec![(RequirementOperator::Tilde, "13.0".parse().unwrap())]),
+            DependencyType::Development,
+        ))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn dump_quick_spec_layout() {
+    let dump = dump_quick_spec(&spec("ruby")).unwrap();
+
+    // `Marshal.dump(spec)` wraps the 19-element `_dump` array.
+    assert!(dump.starts_with(b"\x04\x08u:\x17Gem::Specification"));
+    assert!(contains(&dump, b"\x04\x08[\x18I\""));
+    // The first string defines `:E`, so later ones link to it.
+    assert!(contains(
+        &dump,
+        b"U:\x11Gem::Version[\x06I\"\x0a1.0.0\x06;\x00T"
+    ));
+    assert!(contains(
+        &dump,
+        b"Iu:\x09Time\x0d\x40\x00\x1f\xc0\x00\x00\x00\x00\x06:\x09zoneI\"\x08UTC\x06;\x00F"
+    ));
+    assert!(contains(
+        &dump,
+        b"o:\x14Gem::Dependency\x0a:\x0a@nameI\"\x09rake"
+    ));
+    assert!(contains(&dump, b":\x0a@type:\x10development"));
+    assert!(contains(&dump, b"I\"\x09ruby\x06;\x00T"));
+}
+
+#[test]
+fn dump_quick_spec_platform() {
+    let dump = dump_quick_spec(&spec("x86_64-linux")).unwrap();
+
+    assert!(contains(
+        &dump,
+        b"o:\x12Gem::Platform\x08:\x09@cpuI\"\x0bx86_64\x06;\x00T:\x08@osI\"\x0alinux\x06;\x00T:\x0d@version0"
+    ));
+}
+
+#[test]
+fn compress_rz_round_trips() {
+    let dump = dump_quick_spec(&spec("ruby")).unwrap();
+    let rz = compress_rz(&dump).unwrap();
+
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(&rz[..])
+        .read_to_end(&mut inflated)
+        .unwrap();
+    assert_eq!(inflated, dump);
+}
+
+#[test]
+fn load_quick_spec_round_trips() {
+    for platform in ["ruby", "x86_64-linux"] {
+        let mut spec = spec(platform);
+        spec.description = Some("Says hello, at length".to_string());
+        spec.email = vec!["jane@example.com".to_string()];
+        spec.metadata.insert(
+            "source_code_uri".to_string(),
+            "https://example.com".to_string(),
+        );
+        let loaded =
+            load_quick_spec(&compress_rz(&dump_quick_spec(&spec).unwrap()).unwrap()).unwrap();
+
+        assert_eq!(loaded.name, spec.name);
+        assert_eq!(loaded.version, spec.version);
+        assert_eq!(loaded.platform, spec.platform);
+        assert_eq!(loaded.original_platform, None);
+        assert_eq!(loaded.date, spec.date);
+        assert_eq!(loaded.summary, spec.summary);
+        assert_eq!(loaded.description, spec.description);
+        assert_eq!(loaded.authors, spec.authors);
+        assert_eq!(loaded.email, spec.email);
+        assert_eq!(loaded.licenses, spec.licenses);
+        assert_eq!(loaded.metadata, spec.metadata);
+        assert_eq!(loaded.dependencies, spec.dependencies);
+        assert_eq!(loaded.rubygems_version, spec.rubygems_version);
+        assert_eq!(loaded.specification_version, spec.specification_version);
+        assert_eq!(
+            loaded.required_ruby_version,
+            Some(spec.required_ruby_version.unwrap_or_default())
+        );
+        assert!(loaded.files.is_empty());
+    }
+}
+
+#[test]
+fn load_quick_spec_rejects_garbage() {
+    assert!(load_quick_spec(b"not zlib").is_err());
+
+    let not_a_spec = compress_rz(b"\x04\x08[\x00").unwrap();
+    assert!(matches!(
+        load_quick_spec(&not_a_spec),
+        Err(gemspec_rs::gem::Error::Marshal(_))
+    ));
+}
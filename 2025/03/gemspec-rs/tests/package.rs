@@ -0,0 +1,707 @@
+use std::{fs::File, io::Read, path::Path, sync::Mutex};
+
+use gemspec_rs::gem::{
+    DEFAULT_MAX_METADATA_SIZE, Dependency, DependencyType, Error, Package, Requirement,
+    SignatureStatus, Specification, normalize_entry_path, process_cache_dir,
+};
+
+fn fixture(name: &str) -> Package<File> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    Package::from_path(&path).unwrap()
+}
+
+#[test]
+fn pure_ruby_specification() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let spec = package.specification().unwrap();
+
+    assert_eq!(spec.name, "hello-world");
+    assert_eq!(spec.version.as_str(), "1.0.0");
+    assert_eq!(spec.platform.as_str(), "ruby");
+    assert_eq!(spec.full_name(), "hello-world-1.0.0");
+}
+
+#[test]
+fn platform_specification() {
+    let mut package = fixture("hello-world-1.0.0-x86_64-linux.gem");
+    let spec = package.specification().unwrap();
+
+    assert_eq!(spec.name, "hello-world");
+    assert_eq!(spec.version.as_str(), "1.0.0");
+    assert_eq!(spec.platform.as_str(), "x86_64-linux");
+    assert_eq!(spec.full_name(), "hello-world-1.0.0-x86_64-linux");
+}
+
+#[test]
+fn specification_resolves_aliases() {
+    let spec = fixture("aliased-versions.gem").specification().unwrap();
+
+    // Only the first document is read, as Psych does.
+    assert_eq!(spec.full_name(), "aliased-versions-1.0.0");
+    assert_eq!(
+        spec.dependencies,
+        [Dependency::new(
+            "rake".to_string(),
+            Requirement::pessimistic("13.0".parse().unwrap()),
+            DependencyType::Runtime,
+        )]
+    );
+    assert_eq!(spec.required_ruby_version, Some(Requirement::default()));
+    assert_eq!(spec.required_rubygems_version, Some(Requirement::default()));
+}
+
+#[test]
+fn uncompressed_metadata() {
+    // A plain `metadata` member, and plain YAML under `metadata.gz`.
+    for name in ["uncompressed-metadata", "plain-metadata-gz"] {
+        let mut package = fixture(&format!("{name}.gem"));
+        let spec = package.specification().unwrap();
+        assert_eq!(spec.full_name(), format!("{name}-1.0.0"));
+        assert_eq!(package.files().unwrap(), spec.files);
+    }
+}
+
+#[test]
+fn each_entry_visits_every_file() {
+    for (name, count) in [
+        ("hello-world-1.0.0.gem", 5),
+        ("hello-world-1.0.0-x86_64-linux.gem", 6),
+    ] {
+        let mut package = fixture(name);
+        let mut entries = 0;
+        package
+            .each_entry(|_| {
+                entries += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(entries, count, "{name}");
+    }
+}
+
+#[test]
+fn specification_matches_each_entry() {
+    let mut package = fixture("hello-world-1.0.0-x86_64-linux.gem");
+    let spec = package.specification().unwrap();
+
+    let mut paths = Vec::new();
+    package
+        .each_entry(|entry| {
+            paths.push(entry.path()?.to_string_lossy().into_owned());
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(paths, spec.files);
+}
+
+#[test]
+fn each_entry_resets_the_archive() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    package.each_entry(|_| Ok(())).unwrap();
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+
+    // Also when `f` fails partway through.
+    let mut package = fixture("hello-world-1.0.0.gem");
+    assert!(matches!(
+        package.each_entry(|_| Err(Error::MissingData)),
+        Err(Error::MissingData)
+    ));
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+    assert_eq!(package.files().unwrap().len(), 5);
+}
+
+#[test]
+fn supports_ruby() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let spec = package.specification().unwrap();
+
+    assert!(spec.supports_ruby(&"3.3.5".parse().unwrap()).unwrap());
+    assert!(!spec.supports_ruby(&"2.7.8".parse().unwrap()).unwrap());
+    assert!(spec.supports_rubygems(&"3.5.3".parse().unwrap()).unwrap());
+}
+
+#[test]
+fn data_tarball_sha256_matches_checksums() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+
+    assert_eq!(
+        package.data_tarball_sha256().unwrap(),
+        "f4e3794640bba931f568566a79fba828143ba022c679a804098ef602e13376d4"
+    );
+    // The archive is reset, so it can be read again.
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+}
+
+#[test]
+fn sha256_file() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hello-world-1.0.0.gem");
+    let file = File::open(path).unwrap();
+    assert_eq!(
+        gemspec_rs::gem::sha256_file(file).unwrap(),
+        "eec407a8738bf413bd7f8bf10ec799fcfa8880c700a13416109aa4b4caa32ffe"
+    );
+    assert_eq!(
+        gemspec_rs::gem::sha256_file(&b""[..]).unwrap(),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+}
+
+#[test]
+fn source_date_epoch() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    assert_eq!(package.source_date_epoch().unwrap(), Some(1704153600));
+    // The archive is reset, so it can be read again.
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+
+    // Entries with differing mtimes have no single epoch.
+    let mut package = fixture("duplicate-entries.gem");
+    assert_eq!(package.source_date_epoch().unwrap(), None);
+}
+
+#[test]
+fn zstd_members() {
+    let mut package = fixture("hello-world-1.0.0-zstd.gem");
+    let spec = package.specification().unwrap();
+
+    assert_eq!(spec.full_name(), "hello-world-1.0.0");
+    assert_eq!(package.files().unwrap(), spec.files);
+}
+
+#[test]
+fn spec_and_files_matches_separate_reads() {
+    for name in [
+        "hello-world-1.0.0.gem",
+        "hello-world-1.0.0-x86_64-linux.gem",
+        "hello-world-1.0.0-zstd.gem",
+        "dot-slash-paths.gem",
+    ] {
+        let (spec, files) = fixture(name).spec_and_files().unwrap();
+        let mut package = fixture(name);
+        assert_eq!(spec, package.specification().unwrap(), "{name}");
+        let paths: Vec<_> = files.iter().map(|file| file.path.clone()).collect();
+        assert_eq!(paths, package.files().unwrap(), "{name}");
+    }
+
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let (_, files) = package.spec_and_files().unwrap();
+    let version = files
+        .iter()
+        .find(|file| file.path == "lib/hello_world/version.rb")
+        .unwrap();
+    assert_eq!(version.size, 42);
+    assert_eq!(version.link_name, None);
+    // The archive is reset afterwards, so other reads still work.
+    assert_eq!(package.files().unwrap().len(), files.len());
+
+    assert!(matches!(
+        fixture("metadata-bomb.gem").spec_and_files(),
+        Err(Error::DecompressedTooLarge { .. })
+    ));
+}
+
+#[test]
+fn failed_reads_reset_the_archive() {
+    let mut package = fixture("truncated-metadata.gem");
+    assert!(package.spec_and_files().is_err());
+    assert!(package.specification().is_err());
+    let files = package.files().unwrap();
+    assert!(!files.is_empty());
+
+    let mut package = fixture("metadata-bomb.gem");
+    assert!(package.spec_and_files().is_err());
+    assert!(package.metadata_bytes().is_err());
+    assert_eq!(package.members().unwrap().len(), 2);
+}
+
+#[test]
+fn read_file() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+
+    assert_eq!(
+        package.read_file("lib/hello_world/version.rb").unwrap(),
+        Some(b"module HelloWorld\n  VERSION = \"1.0.0\"\nend\n".to_vec())
+    );
+    assert_eq!(package.read_file("lib/missing.rb").unwrap(), None);
+    // The path is normalized the same way entry paths are.
+    assert_eq!(
+        package.read_file("./lib//hello_world/version.rb").unwrap(),
+        package.read_file("lib/hello_world/version.rb").unwrap()
+    );
+    assert!(matches!(
+        package.read_file("../lib/hello_world/version.rb"),
+        Err(Error::UnsafePath(_))
+    ));
+    assert_eq!(package.files().unwrap().len(), 5);
+}
+
+#[test]
+fn each_entry_with_progress() {
+    for (name, has_total) in [
+        ("hello-world-1.0.0.gem", false),
+        ("hello-world-1.0.0-zstd.gem", true),
+    ] {
+        let mut package = fixture(name);
+        let mut progress = Vec::new();
+        package
+            .each_entry_with_progress(
+                |processed, total| progress.push((processed, total)),
+                |_| Ok(()),
+            )
+            .unwrap();
+
+        assert_eq!(progress.len(), 5, "{name}");
+        assert!(progress.is_sorted(), "{name}");
+        let (processed, total) = progress.last().copied().unwrap();
+        assert_eq!(total.is_some(), has_total, "{name}");
+        assert!(total.is_none_or(|total| processed <= total), "{name}");
+    }
+}
+
+#[test]
+fn metadata_bomb_is_rejected() {
+    let mut package = fixture("metadata-bomb.gem");
+
+    assert!(matches!(
+        package.specification(),
+        Err(Error::DecompressedTooLarge { member, limit })
+            if member == "metadata.gz" && limit == DEFAULT_MAX_METADATA_SIZE
+    ));
+    // The archive is reset, so the data can still be read.
+    assert_eq!(package.files().unwrap().len(), 0);
+}
+
+#[test]
+fn size_limits_are_configurable() {
+    let mut package = fixture("hello-world-1.0.0.gem").with_max_metadata_size(64);
+    assert!(matches!(
+        package.specification(),
+        Err(Error::DecompressedTooLarge { limit: 64, .. })
+    ));
+
+    let mut package = fixture("hello-world-1.0.0.gem").with_max_entry_size(16);
+    assert!(matches!(
+        package.each_entry(|_| Ok(())),
+        Err(Error::DecompressedTooLarge { limit: 16, .. })
+    ));
+    let mut package = fixture("hello-world-1.0.0.gem").with_max_entry_size(16);
+    assert!(matches!(
+        package.read_file("lib/hello_world/version.rb"),
+        Err(Error::DecompressedTooLarge { limit: 16, .. })
+    ));
+}
+
+#[test]
+fn members() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+
+    assert_eq!(
+        package.members().unwrap(),
+        ["metadata.gz", "data.tar.gz", "checksums.yaml.gz"]
+    );
+    let checksums = package.member_bytes("checksums.yaml.gz").unwrap().unwrap();
+    assert!(checksums.starts_with(&[0x1f, 0x8b]));
+    assert_eq!(package.member_bytes("metadata.gz.sig").unwrap(), None);
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+
+    // A member over the size limit fails, but leaves the archive readable.
+    let mut package = fixture("hello-world-1.0.0.gem").with_max_entry_size(16);
+    assert!(matches!(
+        package.member_bytes("data.tar.gz"),
+        Err(Error::DecompressedTooLarge { limit: 16, .. })
+    ));
+    assert_eq!(package.members().unwrap().len(), 3);
+}
+
+#[test]
+fn partial_specification_from_truncated_metadata() {
+    let mut package = fixture("truncated-metadata.gem");
+    assert!(package.specification().is_err());
+
+    let partial = package.partial_specification().unwrap();
+    assert_eq!(partial.name.as_deref(), Some("hello-world"));
+    assert_eq!(partial.version.as_deref(), Some("1.0.0"));
+    assert_eq!(partial.platform.as_deref(), Some("ruby"));
+}
+
+#[test]
+fn process_cache_dir_collects_failures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let names = Mutex::new(Vec::new());
+    let failures = process_cache_dir(&dir, 2, |_, package| {
+        let spec = package.specification()?;
+        names.lock().unwrap().push(spec.full_name());
+        Ok(())
+    })
+    .unwrap();
+
+    let mut names = names.into_inner().unwrap();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "aliased-versions-1.0.0",
+            "expired-certificate-1.0.0",
+            "hello-world-1.0.0",
+            "hello-world-1.0.0",
+            "hello-world-1.0.0",
+            "hello-world-1.0.0",
+            "hello-world-1.0.0",
+            "hello-world-1.0.0-x86_64-linux",
+            "plain-metadata-gz-1.0.0",
+            "signed-1.0.0",
+            "tampered-signature-1.0.0",
+            "uncompressed-metadata-1.0.0"
+        ]
+    );
+    let failed: Vec<_> = failures
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(failed, ["metadata-bomb.gem", "truncated-metadata.gem"]);
+}
+
+#[test]
+fn from_bytes() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hello-world-1.0.0.gem");
+    let mut package = Package::from_bytes(std::fs::read(path).unwrap());
+
+    assert_eq!(
+        package.specification().unwrap().full_name(),
+        "hello-world-1.0.0"
+    );
+    assert_eq!(package.files().unwrap().len(), 5);
+}
+
+#[test]
+fn corrupt_outer_archive_is_an_error() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hello-world-1.0.0.gem");
+    let bytes = std::fs::read(path).unwrap();
+
+    // A bad checksum on the first header, and a cut inside the header of
+    // `data.tar.gz`, both fail to find the data rather than panicking.
+    let mut corrupt = bytes.clone();
+    corrupt[148..156].copy_from_slice(b"0000000\0");
+    let data = bytes
+        .windows(11)
+        .position(|window| window == b"data.tar.gz")
+        .unwrap();
+    for bytes in [corrupt.clone(), bytes[..data + 100].to_vec()] {
+        let mut package = Package::from_bytes(bytes);
+        assert!(matches!(package.data_tarball_sha256(), Err(Error::Io(_))));
+        assert!(matches!(
+            package.read_file("lib/hello_world.rb"),
+            Err(Error::Io(_))
+        ));
+        assert!(matches!(package.files(), Err(Error::Io(_))));
+    }
+    assert!(matches!(
+        Package::from_bytes(corrupt).specification(),
+        Err(Error::Io(_))
+    ));
+
+    // A cut inside the compressed data is only found once it's read.
+    let mut package = Package::from_bytes(bytes[..data + 530].to_vec());
+    assert!(matches!(package.files(), Err(Error::Io(_))));
+}
+
+#[test]
+fn from_path_missing_file() {
+    assert!(matches!(
+        Package::from_path(Path::new("tests/fixtures/missing.gem")),
+        Err(Error::Io(_))
+    ));
+}
+
+#[test]
+fn recorded_checksums() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let checksums = package.recorded_checksums().unwrap();
+
+    assert_eq!(
+        checksums.sha256.keys().collect::<Vec<_>>(),
+        ["data.tar.gz", "metadata.gz"]
+    );
+    assert_eq!(
+        checksums.sha256["data.tar.gz"],
+        package.data_tarball_sha256().unwrap()
+    );
+    assert_eq!(checksums.sha512.len(), 2);
+    package.verify_checksums().unwrap();
+
+    // Built without a checksums member.
+    let mut package = fixture("metadata-bomb.gem");
+    assert_eq!(package.recorded_checksums().unwrap(), Default::default());
+}
+
+#[test]
+fn verify_checksums_detects_tampering() {
+    use std::io::Write;
+
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let mut checksums = package.recorded_checksums().unwrap();
+    let expected = checksums.sha256["data.tar.gz"].clone();
+    checksums
+        .sha256
+        .insert("data.tar.gz".to_string(), "0".repeat(64));
+
+    let mut yaml = String::from("---\nSHA256:\n");
+    for (member, sha256) in &checksums.sha256 {
+        yaml.push_str(&format!("  {member}: {sha256}\n"));
+    }
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(yaml.as_bytes()).unwrap();
+    let gz = gz.finish().unwrap();
+
+    let mut builder = tar::Builder::new(Vec::new());
+    for member in ["metadata.gz", "data.tar.gz"] {
+        let bytes = package.member_bytes(member).unwrap().unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, member, bytes.as_slice())
+            .unwrap();
+    }
+    let mut header = tar::Header::new_gnu();
+    header.set_size(gz.len() as u64);
+    header.set_mode(0o644);
+    builder
+        .append_data(&mut header, "checksums.yaml.gz", gz.as_slice())
+        .unwrap();
+
+    let mut tampered = Package::from_bytes(builder.into_inner().unwrap());
+    assert!(matches!(
+        tampered.verify_checksums(),
+        Err(Error::ChecksumMismatch { member, actual, .. })
+            if member == "data.tar.gz" && actual == expected
+    ));
+}
+
+#[test]
+fn installed_size() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+
+    assert_eq!(package.installed_size().unwrap(), 242);
+    assert_eq!(package.files().unwrap().len(), 5);
+}
+
+#[test]
+fn specification_is_cached_across_entry_reads() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let spec = package.specification().unwrap();
+
+    assert_eq!(package.files().unwrap(), spec.files);
+    assert_eq!(package.specification().unwrap(), spec);
+    package.verify_checksums().unwrap();
+    assert_eq!(package.specification().unwrap(), spec);
+    assert_eq!(package.files().unwrap(), spec.files);
+}
+
+#[test]
+fn verify_files_against_recorded_digests() {
+    use sha2::Digest;
+
+    let mut package = fixture("hello-world-1.0.0.gem");
+    let mut expected = std::collections::HashMap::new();
+    for path in package.files().unwrap() {
+        let contents = package.read_file(&path).unwrap().unwrap();
+        expected.insert(path, <[u8; 32]>::from(sha2::Sha256::digest(&contents)));
+    }
+    assert_eq!(
+        package.verify_files(&expected).unwrap(),
+        Vec::<String>::new()
+    );
+
+    expected.insert("README.md".to_string(), [0; 32]);
+    expected.remove("LICENSE.txt");
+    expected.insert("lib/removed.rb".to_string(), [0; 32]);
+    assert_eq!(
+        package.verify_files(&expected).unwrap(),
+        ["LICENSE.txt", "README.md", "lib/removed.rb"]
+    );
+}
+
+#[test]
+fn specification_serialize_round_trips() {
+    for name in [
+        "hello-world-1.0.0.gem",
+        "hello-world-1.0.0-x86_64-linux.gem",
+    ] {
+        let spec = fixture(name).specification().unwrap();
+
+        let yaml = serde_yaml::to_string(&spec).unwrap();
+        assert_eq!(Specification::from_yaml(&yaml).unwrap(), spec, "{name}");
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Specification>(&json).unwrap(),
+            spec,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn buffer_capacity_does_not_change_contents() {
+    for name in ["hello-world-1.0.0.gem", "hello-world-1.0.0-zstd.gem"] {
+        let mut expected = fixture(name);
+        for capacity in [1, 64, 1 << 20] {
+            let mut package = fixture(name).with_buffer_capacity(capacity);
+            assert_eq!(
+                package.specification().unwrap(),
+                expected.specification().unwrap(),
+                "{name} {capacity}"
+            );
+            assert_eq!(
+                package.read_file("lib/hello_world/version.rb").unwrap(),
+                expected.read_file("lib/hello_world/version.rb").unwrap(),
+                "{name} {capacity}"
+            );
+        }
+    }
+}
+
+#[test]
+fn entry_paths_are_normalized() {
+    let mut expected = fixture("hello-world-1.0.0.gem");
+    let mut package = fixture("dot-slash-paths.gem");
+
+    // Entries are stored as `./lib//hello_world.rb` and the like.
+    let mut raw = Vec::new();
+    package
+        .each_entry(|entry| {
+            raw.push(entry.path()?.to_string_lossy().into_owned());
+            Ok(())
+        })
+        .unwrap();
+    assert!(raw.iter().all(|path| path.starts_with("./")), "{raw:?}");
+
+    let mut package = fixture("dot-slash-paths.gem");
+    assert_eq!(package.files().unwrap(), expected.files().unwrap());
+    assert_eq!(
+        package.read_file("lib/hello_world/version.rb").unwrap(),
+        expected.read_file("lib/hello_world/version.rb").unwrap()
+    );
+    assert_eq!(package.validate_manifest().unwrap(), Vec::<String>::new());
+
+    assert_eq!(
+        normalize_entry_path(".//lib/./foo.rb/").unwrap(),
+        "lib/foo.rb"
+    );
+    for path in ["/etc/passwd", "lib/../../evil.rb", "../evil.rb"] {
+        assert!(matches!(
+            normalize_entry_path(path),
+            Err(Error::UnsafePath(p)) if p == path
+        ));
+    }
+}
+
+#[test]
+fn unsafe_entry_paths_are_rejected() {
+    let mut package = fixture("unsafe-paths.gem");
+
+    assert!(matches!(
+        package.files(),
+        Err(Error::UnsafePath(path)) if path == "../evil.rb"
+    ));
+    let mut package = fixture("unsafe-paths.gem");
+    assert!(matches!(
+        package.each_entry(|_| Ok(())),
+        Err(Error::UnsafePath(_))
+    ));
+    let mut package = fixture("unsafe-paths.gem");
+    assert_eq!(package.specification().unwrap().name, "hello-world");
+}
+
+#[test]
+fn is_empty_data() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    assert!(!package.is_empty_data().unwrap());
+    assert_eq!(package.files().unwrap().len(), 5);
+
+    // Its metadata is unreadable, but the empty data can still be checked.
+    let mut package = fixture("metadata-bomb.gem");
+    assert!(package.is_empty_data().unwrap());
+}
+
+#[test]
+fn each_entry_matching() {
+    let mut pattern = globset::GlobSetBuilder::new();
+    pattern.add(globset::Glob::new("lib/**/*.rb").unwrap());
+    pattern.add(globset::Glob::new("*.md").unwrap());
+    let pattern = pattern.build().unwrap();
+
+    for name in ["hello-world-1.0.0.gem", "dot-slash-paths.gem"] {
+        let mut package = fixture(name);
+        let mut paths = Vec::new();
+        package
+            .each_entry_matching(&pattern, |entry| {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents)?;
+                paths.push(gemspec_rs::gem::entry_path(entry)?);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            paths,
+            [
+                "README.md",
+                "lib/hello_world.rb",
+                "lib/hello_world/version.rb"
+            ],
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn duplicate_files() {
+    let mut package = fixture("hello-world-1.0.0.gem");
+    assert_eq!(package.duplicate_files().unwrap(), Vec::<String>::new());
+
+    // `lib/hello_world.rb` is repeated, and `README.md` again as `./README.md`.
+    let mut package = fixture("duplicate-entries.gem");
+    assert_eq!(
+        package.duplicate_files().unwrap(),
+        ["README.md", "lib/hello_world.rb"]
+    );
+    assert_eq!(package.files().unwrap().len(), 7);
+}
+
+#[test]
+fn verify_signature() {
+    assert_eq!(
+        fixture("signed.gem").verify_signature().unwrap(),
+        SignatureStatus::Valid
+    );
+    assert_eq!(
+        fixture("hello-world-1.0.0.gem").verify_signature().unwrap(),
+        SignatureStatus::Unsigned
+    );
+    // Unsigned whatever the metadata member is called.
+    for name in ["uncompressed-metadata.gem", "hello-world-1.0.0-zstd.gem"] {
+        assert_eq!(
+            fixture(name).verify_signature().unwrap(),
+            SignatureStatus::Unsigned,
+            "{name}"
+        );
+    }
+
+    let SignatureStatus::Invalid(reason) = fixture("tampered-signature.gem")
+        .verify_signature()
+        .unwrap()
+    else {
+        panic!("tampered signature verified");
+    };
+    assert!(reason.contains("does not match metadata.gz"), "{reason}");
+
+    // The chain expired in 2021, before the spec's 2024 date.
+    let SignatureStatus::Invalid(reason) = fixture("expired-certificate.gem")
+        .verify_signature()
+        .unwrap()
+    else {
+        panic!("expired certificate verified");
+    };
+    assert!(reason.contains("is not valid at 2024-01-02"), "{reason}");
+}
@@ -0,0 +1,52 @@
+use gemspec_rs::gem::PackageEntry;
+
+fn entry(
+    path: &'static str,
+    mode: u32,
+    link_name: Option<&'static str>,
+    magic: &str,
+) -> PackageEntry<'static> {
+    PackageEntry {
+        gem: "hello-world",
+        version: "1.0.0",
+        platform: "ruby",
+        size: 0,
+        path,
+        link_name,
+        mode,
+        uid: 0,
+        gid: 0,
+        mtime: 0,
+        sha256: Default::default(),
+        magic: magic.to_string(),
+    }
+}
+
+#[test]
+fn is_executable() {
+    assert!(entry("exe/hello-world", 0o755, None, "text/x-ruby").is_executable());
+    assert!(entry("exe/hello-world", 0o744, None, "text/x-ruby").is_executable());
+    assert!(entry("exe/hello-world", 0o601, None, "text/x-ruby").is_executable());
+    assert!(!entry("lib/hello_world.rb", 0o644, None, "text/x-ruby").is_executable());
+}
+
+#[test]
+fn is_symlink() {
+    assert!(
+        entry(
+            "bin/hello-world",
+            0o777,
+            Some("../exe/hello-world"),
+            "inode/symlink"
+        )
+        .is_symlink()
+    );
+    assert!(!entry("exe/hello-world", 0o755, None, "text/x-ruby").is_symlink());
+}
+
+#[test]
+fn is_binary() {
+    assert!(!entry("README.md", 0o644, None, "text/markdown").is_binary());
+    assert!(entry("lib/hello_world.so", 0o755, None, "application/x-sharedlib").is_binary());
+    assert!(entry("data.bin", 0o644, None, "application/octet-stream").is_binary());
+}
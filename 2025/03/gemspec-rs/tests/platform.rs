@@ -0,0 +1,122 @@
+use gemspec_rs::gem::{KnownPlatform, Platform};
+use strum::IntoEnumIterator;
+
+#[test]
+fn known_platforms_round_trip() {
+    for name in [
+        "ruby",
+        "java",
+        "x86_64-linux",
+        "aarch64-linux-musl",
+        "arm64-darwin",
+        "universal-darwin",
+        "x86-mingw32",
+        "x64-mingw-ucrt",
+    ] {
+        let known: KnownPlatform = name.parse().unwrap();
+        assert_eq!(known.to_string(), name);
+        assert_eq!(Platform::new(name).known(), Some(known));
+        assert_eq!(Platform::from(known).as_str(), name);
+    }
+}
+
+#[test]
+fn every_known_platform_parses_back() {
+    for known in KnownPlatform::iter() {
+        let platform: Platform = known.to_string().parse().unwrap();
+        assert_eq!(platform.known(), Some(known), "{known}");
+    }
+    assert_eq!(
+        "x64-mingw-ucrt".parse::<Platform>().unwrap().known(),
+        Some(KnownPlatform::X64MingwUcrt)
+    );
+}
+
+#[test]
+fn unknown_platforms() {
+    assert_eq!(Platform::new("arm64-darwin-23").known(), None);
+    assert_eq!(Platform::new("x86_64_linux").known(), None);
+    assert_eq!(
+        Platform::new("x86_64-linux-musl").known(),
+        Some(KnownPlatform::X86_64LinuxMusl)
+    );
+}
+
+#[test]
+fn cpu_os_and_version() {
+    for (name, cpu, os, version) in [
+        ("ruby", None, None, None),
+        ("java", None, Some("java"), None),
+        (
+            "universal-java-17",
+            Some("universal"),
+            Some("java"),
+            Some("17"),
+        ),
+        ("x86_64-linux", Some("x86_64"), Some("linux"), None),
+        (
+            "aarch64-linux-musl",
+            Some("aarch64"),
+            Some("linux"),
+            Some("musl"),
+        ),
+        ("arm64-darwin-23", Some("arm64"), Some("darwin"), Some("23")),
+        ("universal-darwin", Some("universal"), Some("darwin"), None),
+        ("i686-linux", Some("x86"), Some("linux"), None),
+        ("x64-mingw-ucrt", Some("x64"), Some("mingw"), Some("ucrt")),
+        ("amd64-freebsd", Some("x86_64"), Some("freebsd"), None),
+        ("x86-mswin32-60", Some("x86"), Some("mswin32"), Some("60")),
+        ("not a platform", None, None, None),
+    ] {
+        let platform = Platform::new(name);
+        assert_eq!(platform.cpu(), cpu, "{name}");
+        assert_eq!(platform.os(), os, "{name}");
+        assert_eq!(platform.version(), version, "{name}");
+    }
+}
+
+#[test]
+fn wasi_platforms() {
+    for (name, known, version) in [
+        ("wasm32-wasi", KnownPlatform::Wasm32Wasi, None),
+        ("wasm32-p1-wasi", KnownPlatform::Wasm32P1Wasi, Some("p1")),
+    ] {
+        let platform = Platform::new(name);
+        assert_eq!(platform.cpu(), Some("wasm32"), "{name}");
+        assert_eq!(platform.os(), Some("wasi"), "{name}");
+        assert_eq!(platform.version(), version, "{name}");
+        assert_eq!(platform.known(), Some(known), "{name}");
+        assert_eq!(name.parse::<Platform>().unwrap(), platform, "{name}");
+    }
+    assert_eq!(
+        Platform::new("wasm32-wasi").specificity(&Platform::new("wasm32-p1-wasi")),
+        Some(3)
+    );
+    assert!("wasm32-px-wasi".parse::<Platform>().is_err());
+}
+
+#[test]
+fn local_platform() {
+    let local = Platform::local();
+    assert!(local.cpu().is_some(), "{local:?}");
+    assert_eq!(local.specificity(&local), Some(3), "{local:?}");
+    if cfg!(all(
+        target_arch = "x86_64",
+        target_os = "linux",
+        target_env = "gnu"
+    )) {
+        assert_eq!(local.known(), Some(KnownPlatform::X86_64Linux));
+    }
+    if cfg!(all(target_arch = "x86_64", target_os = "windows")) {
+        assert_eq!(local.known(), Some(KnownPlatform::X64MingwUcrt));
+    }
+}
+
+#[test]
+fn display_matches_as_str() {
+    for name in ["ruby", "x86_64-linux", "arm64-darwin-23"] {
+        let platform: Platform = name.parse().unwrap();
+        assert_eq!(platform.to_string(), platform.as_str());
+    }
+    assert_eq!(Platform::default().to_string(), "ruby");
+}
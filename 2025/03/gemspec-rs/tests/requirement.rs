@@ -0,0 +1,421 @@
+use gemspec_rs::gem::{Dependency, DependencyType, Requirement, RequirementOperator, Version};
+
+fn version(version: &str) -> Version {
+    version.parse().unwrap()
+}
+
+fn requirement(requirements: &[(RequirementOperator, &str)]) -> Requirement {
+    Requirement::new(
+        requirements
+            .iter()
+            .map(|(op, v)| (op.clone(), version(v)))
+            .collect(),
+    )
+}
+
+#[test]
+fn versions_compare_like_rubygems() {
+    assert_eq!(version("1.0"), version("1.0.0"));
+    assert!(version("1.10") > version("1.9"));
+    assert!(version("1.0.0.rc1") < version("1.0.0"));
+    assert!(version("1.0.0a") < version("1.0.0.b"));
+    assert!(version("1.0.0.pre.1") < version("1.0.0.pre.2"));
+}
+
+#[test]
+fn satisfied_by() {
+    use RequirementOperator::*;
+
+    let range = requirement(&[(GreaterThanOrEqual, "1.0"), (LessThan, "2.0")]);
+    assert!(range.satisfied_by(&version("1.5")).unwrap());
+    assert!(!range.satisfied_by(&version("2.0")).unwrap());
+
+    let tilde = requirement(&[(Tilde, "3.4.1")]);
+    assert!(tilde.satisfied_by(&version("3.4.9")).unwrap());
+    assert!(!tilde.satisfied_by(&version("3.5")).unwrap());
+    assert!(!tilde.satisfied_by(&version("3.4.0")).unwrap());
+
+    let pessimistic = requirement(&[(Tilde, "3.4")]);
+    assert!(pessimistic.satisfied_by(&version("3.99")).unwrap());
+    assert!(!pessimistic.satisfied_by(&version("4.0.0.rc1")).unwrap());
+
+    let glued = requirement(&[(Tilde, "1.2.3a")]);
+    assert!(glued.satisfied_by(&version("1.2.4")).unwrap());
+    assert!(!glued.satisfied_by(&version("1.9")).unwrap());
+
+    assert!(
+        requirement(&[(NotEqual, "1.0")])
+            .satisfied_by(&version("1.0.1"))
+            .unwrap()
+    );
+    assert!(
+        !requirement(&[(Equal, "1.0")])
+            .satisfied_by(&version("1.0.1"))
+            .unwrap()
+    );
+}
+
+#[test]
+fn as_exact() {
+    use RequirementOperator::*;
+
+    assert_eq!(
+        requirement(&[(Equal, "1.2.3")]).as_exact(),
+        Some(&version("1.2.3"))
+    );
+    assert_eq!(
+        requirement(&[(GreaterThanOrEqual, "1.2.3")]).as_exact(),
+        None
+    );
+    assert_eq!(
+        requirement(&[(GreaterThanOrEqual, "1.2.3"), (LessThanOrEqual, "1.2.3")]).as_exact(),
+        None
+    );
+    assert_eq!(
+        requirement(&[(Equal, "1.2.3"), (Equal, "1.2.3")]).as_exact(),
+        None
+    );
+}
+
+#[test]
+fn prereleases_need_a_prerelease_requirement() {
+    use RequirementOperator::*;
+
+    let release = requirement(&[(GreaterThanOrEqual, "1.0")]);
+    assert!(!release.allows_prerelease());
+    assert!(release.satisfied_by(&version("2.0.rc1")).unwrap());
+    assert!(
+        !release
+            .satisfied_by_with_policy(&version("2.0.rc1"), false)
+            .unwrap()
+    );
+    assert!(
+        release
+            .satisfied_by_with_policy(&version("2.0.rc1"), true)
+            .unwrap()
+    );
+    assert!(
+        release
+            .satisfied_by_with_policy(&version("2.0"), false)
+            .unwrap()
+    );
+
+    let prerelease = requirement(&[(GreaterThanOrEqual, "2.0.rc1")]);
+    assert!(prerelease.allows_prerelease());
+    assert!(
+        prerelease
+            .satisfied_by_with_policy(&version("2.0.rc1"), false)
+            .unwrap()
+    );
+    assert!(
+        !prerelease
+            .satisfied_by_with_policy(&version("1.9"), false)
+            .unwrap()
+    );
+}
+
+#[test]
+fn for_approximate() {
+    let approximate = |v: &str| Requirement::for_approximate(&version(v)).to_string();
+    assert_eq!(approximate("3.4.1"), "~> 3.4");
+    assert_eq!(approximate("3.4"), "~> 3.4");
+    assert_eq!(approximate("3"), "~> 3.0");
+    assert_eq!(approximate("1.2.3.rc1"), "~> 1.2.a");
+
+    // The prerelease recommendation admits the prerelease it came from.
+    let prerelease = version("1.2.3.rc1");
+    assert!(
+        Requirement::for_approximate(&prerelease)
+            .satisfied_by(&prerelease)
+            .unwrap()
+    );
+}
+
+#[test]
+fn to_semver_range() {
+    use RequirementOperator::*;
+
+    let range = |requirements: &[(RequirementOperator, &str)]| {
+        requirement(requirements)
+            .to_semver_range()
+            .map(|range| range.to_string())
+    };
+    assert_eq!(range(&[(Tilde, "1.2")]).unwrap(), ">=1.2.0, <2.0.0");
+    assert_eq!(
+        range(&[(GreaterThan, "1"), (LessThanOrEqual, "2.0.rc1")]).unwrap(),
+        ">1.0.0, <=2.0.0-rc1"
+    );
+    assert_eq!(range(&[(Equal, "1.2.3")]).unwrap(), "=1.2.3");
+
+    let semver = requirement(&[(Tilde, "1.2.3")]).to_semver_range().unwrap();
+    assert!(semver.matches(&"1.2.9".parse().unwrap()));
+    assert!(!semver.matches(&"1.3.0".parse().unwrap()));
+
+    for requirements in [
+        &[(NotEqual, "1.0")][..],
+        &[(GreaterThanOrEqual, "1.2.3.4")],
+        &[(Unknown("=~".to_string()), "1.0")],
+    ] {
+        assert!(
+            matches!(
+                range(requirements),
+                Err(gemspec_rs::gem::Error::Requirement(_))
+            ),
+            "{requirements:?}"
+        );
+    }
+}
+
+#[test]
+fn simplify() {
+    use RequirementOperator::*;
+
+    let simplified = |requirements: &[(RequirementOperator, &str)]| {
+        requirement(requirements).simplify().to_string()
+    };
+    assert_eq!(
+        simplified(&[
+            (GreaterThanOrEqual, "1.0"),
+            (GreaterThanOrEqual, "0.5"),
+            (LessThan, "3.0"),
+            (LessThan, "2.0")
+        ]),
+        ">= 1.0, < 2.0"
+    );
+    assert_eq!(
+        simplified(&[(GreaterThanOrEqual, "1.0"), (GreaterThan, "1.0")]),
+        "> 1.0"
+    );
+    assert_eq!(
+        simplified(&[(GreaterThanOrEqual, "1.2"), (LessThanOrEqual, "1.2.0")]),
+        "= 1.2"
+    );
+    // A `~>` folds with the other bounds once it's expanded.
+    assert_eq!(
+        simplified(&[(Tilde, "1.2"), (GreaterThanOrEqual, "1.0")]),
+        ">= 1.2, < 2.A"
+    );
+    assert_eq!(
+        simplified(&[(Tilde, "1.2"), (GreaterThanOrEqual, "1.5")]),
+        ">= 1.5, < 2.A"
+    );
+    assert_eq!(
+        simplified(&[(Tilde, "1.2"), (GreaterThanOrEqual, "1.5"), (LessThan, "3")]),
+        ">= 1.5, < 2.A"
+    );
+    assert_eq!(
+        simplified(&[(Tilde, "1.2.3"), (Tilde, "1.2")]),
+        ">= 1.2.3, < 1.3.A"
+    );
+    assert_eq!(
+        simplified(&[
+            (GreaterThanOrEqual, "1.0"),
+            (LessThan, "2.0"),
+            (NotEqual, "1.5"),
+            (NotEqual, "2.5"),
+            (NotEqual, "1.5")
+        ]),
+        ">= 1.0, < 2.0, != 1.5"
+    );
+    assert_eq!(simplified(&[]), ">= 0");
+    // An unknown operator survives a pin, as it does a range.
+    assert_eq!(
+        simplified(&[(Equal, "1.0"), (Unknown("=~".to_string()), "1.0")]),
+        "= 1.0, =~ 1.0"
+    );
+
+    // Simplifying doesn't change which versions are admitted.
+    let mixed = requirement(&[(Tilde, "1.2"), (GreaterThanOrEqual, "1.5")]);
+    for v in ["1.4", "1.5", "1.99", "2.A", "2.0.a", "2.0.rc1", "2.0"] {
+        let v = version(v);
+        assert_eq!(
+            mixed.satisfied_by(&v).unwrap(),
+            mixed.simplify().satisfied_by(&v).unwrap(),
+            "{v:?}"
+        );
+    }
+}
+
+#[test]
+fn simplify_contradictions() {
+    use RequirementOperator::*;
+
+    for requirements in [
+        &[(GreaterThanOrEqual, "3.0"), (LessThan, "2.0")][..],
+        &[(GreaterThan, "2.0"), (LessThanOrEqual, "2.0")],
+        &[(Tilde, "1.2"), (GreaterThanOrEqual, "2.0.rc1")],
+        &[(Equal, "1.0"), (NotEqual, "1.0")],
+    ] {
+        assert!(!requirement(requirements).is_satisfiable());
+        let simplified = requirement(requirements).simplify();
+        assert!(simplified.is_unsatisfiable(), "{simplified}");
+        assert!(!simplified.satisfied_by(&version("0")).unwrap());
+    }
+}
+
+#[test]
+fn negate() {
+    use RequirementOperator::*;
+
+    let negated = |requirements: &[(RequirementOperator, &str)]| {
+        requirement(requirements)
+            .negate()
+            .unwrap()
+            .iter()
+            .map(Requirement::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        negated(&[(GreaterThanOrEqual, "1.0"), (LessThan, "2.0")]),
+        ["< 1.0", ">= 2.0"]
+    );
+    assert_eq!(
+        negated(&[(GreaterThan, "1.0"), (LessThanOrEqual, "2.0")]),
+        ["<= 1.0", "> 2.0"]
+    );
+    assert_eq!(negated(&[(Tilde, "1.2.3")]), ["< 1.2.3", ">= 1.3.A"]);
+    assert_eq!(negated(&[(Equal, "1.0")]), ["!= 1.0"]);
+    assert_eq!(negated(&[(NotEqual, "1.0")]), ["= 1.0"]);
+
+    for range in [
+        requirement(&[(GreaterThanOrEqual, "1.0"), (LessThan, "2.0")]),
+        requirement(&[(Tilde, "1.0")]),
+    ] {
+        let complement = range.negate().unwrap();
+        // Prereleases of a `~>`'s bump fall on exactly one side too.
+        for v in [
+            "0.9", "1.0", "1.5", "2.A", "2.0.A", "2.0.a", "2.0.rc1", "2.0", "2.1",
+        ] {
+            let v = version(v);
+            assert_ne!(
+                range.satisfied_by(&v).unwrap(),
+                complement.iter().any(|r| r.satisfied_by(&v).unwrap()),
+                "{range} {v:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn equality_ignores_constraint_order() {
+    use RequirementOperator::*;
+
+    let forward = requirement(&[
+        (GreaterThanOrEqual, "1.0"),
+        (LessThan, "2.0"),
+        (NotEqual, "1.5"),
+    ]);
+    let backward = requirement(&[
+        (NotEqual, "1.5"),
+        (LessThan, "2.0"),
+        (GreaterThanOrEqual, "1.0"),
+    ]);
+    assert_eq!(forward, backward);
+    assert_eq!(
+        forward.normalized().requirements(),
+        backward.normalized().requirements()
+    );
+    assert_ne!(
+        forward,
+        requirement(&[(GreaterThanOrEqual, "1.0"), (LessThan, "2.0")])
+    );
+
+    let dependency = |requirement: Requirement| {
+        Dependency::new("rake".to_string(), requirement, DependencyType::Runtime)
+    };
+    assert_eq!(dependency(forward.clone()), dependency(backward.clone()));
+
+    // Serialized output is sorted, so it doesn't depend on declaration order.
+    let json = serde_json::to_string(&forward).unwrap();
+    assert_eq!(json, serde_json::to_string(&backward).unwrap());
+    assert_eq!(
+        json,
+        r#"{"requirements":[[">=","1.0"],["<","2.0"],["!=","1.5"]]}"#
+    );
+    assert_eq!(
+        serde_yaml::to_string(&dependency(forward)).unwrap(),
+        serde_yaml::to_string(&dependency(backward)).unwrap()
+    );
+}
+
+#[test]
+fn deserializes_ruby_requirements() {
+    use RequirementOperator::*;
+
+    // `required_ruby_version` from the hello-world fixture's metadata.
+    let node = r#"!ruby/object:Gem::Requirement
+requirements:
+- - ">="
+  - !ruby/object:Gem::Version
+    version: 3.0.0
+"#;
+    assert_eq!(
+        serde_yaml::from_str::<Requirement>(node).unwrap(),
+        requirement(&[(GreaterThanOrEqual, "3.0.0")])
+    );
+
+    let bare = "- - \"~>\"\n  - '1.2'\n- - \"!=\"\n  - 1.2.5\n";
+    assert_eq!(
+        serde_yaml::from_str::<Requirement>(bare).unwrap(),
+        requirement(&[(Tilde, "1.2"), (NotEqual, "1.2.5")])
+    );
+
+    let range = requirement(&[(GreaterThanOrEqual, "1.0"), (LessThan, "2.0")]);
+    let json = serde_json::to_string(&range).unwrap();
+    assert_eq!(serde_json::from_str::<Requirement>(&json).unwrap(), range);
+
+    assert!(serde_yaml::from_str::<Requirement>("requirements: [[\">=\"]]").is_err());
+    assert!(serde_yaml::from_str::<Requirement>("version: 1.0").is_err());
+}
+
+#[test]
+fn unknown_operators_round_trip() {
+    let yaml = "requirements:\n- - \"=~\"\n  - !ruby/object:Gem::Version\n    version: '1.0'\n";
+    let requirement: Requirement = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        requirement.requirements()[0].0,
+        RequirementOperator::Unknown("=~".to_string())
+    );
+    assert_eq!(requirement.to_string(), "=~ 1.0");
+
+    let json = serde_json::to_string(&requirement).unwrap();
+    assert_eq!(json, r#"{"requirements":[["=~","1.0"]]}"#);
+    assert_eq!(
+        serde_json::from_str::<Requirement>(&json).unwrap(),
+        requirement
+    );
+
+    assert!(matches!(
+        requirement.satisfied_by(&version("1.0")),
+        Err(gemspec_rs::gem::Error::Requirement(_))
+    ));
+    assert!(matches!(
+        requirement.negate(),
+        Err(gemspec_rs::gem::Error::Requirement(_))
+    ));
+}
+
+#[test]
+fn parse_multiple_matches_comma_joined() {
+    for clauses in [
+        &[">= 1.0", "< 2.0"][..],
+        &["~> 1.2", "!= 1.2.5"],
+        &["  1.0  "],
+        &["= 1.0.rc1", "<= 2"],
+    ] {
+        let joined: Requirement = clauses.join(", ").parse().unwrap();
+        let multiple = Requirement::parse_multiple(clauses).unwrap();
+        assert_eq!(multiple, joined, "{clauses:?}");
+        assert_eq!(multiple.to_string(), joined.to_string(), "{clauses:?}");
+    }
+    assert_eq!(
+        Requirement::parse_multiple(&["1.0"]).unwrap().to_string(),
+        "= 1.0"
+    );
+    assert_eq!(
+        Requirement::parse_multiple(&[]).unwrap().to_string(),
+        ">= 0"
+    );
+    assert!(Requirement::parse_multiple(&[">= 1.0", "=~ 2"]).is_err());
+    assert!(Requirement::parse_multiple(&[""]).is_err());
+}
@@ -0,0 +1,638 @@
+use gemspec_rs::gem::{
+    Dependency, DependencyType, PartialSpecification, Requirement, RequirementOperator,
+    Specification,
+};
+
+#[test]
+fn builder_fills_defaults() {
+    let spec = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .license("MIT")
+        .add_dependency(Dependency::new(
+            "rake".to_string(),
+            Requirement::new(vec![(RequirementOperator::Tilde, "13.0".parse().unwrap())]),
+            DependencyType::Development,
+        ))
+        .build()
+        .unwrap();
+
+    assert_eq!(spec.full_name(), "hello-world-1.0.0");
+    assert_eq!(spec.platform.as_str(), "ruby");
+    assert_eq!(spec.require_paths, ["lib"]);
+    assert_eq!(spec.licenses, ["MIT"]);
+    assert_eq!(spec.dependencies.len(), 1);
+    assert!(
+        spec.required_ruby_version
+            .as_ref()
+            .is_some_and(Requirement::is_none)
+    );
+}
+
+#[test]
+fn builder_requires_fields() {
+    let missing_summary = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .authors(["Jane Doe"])
+        .build();
+    assert!(missing_summary.is_err());
+
+    let invalid_name = Specification::builder()
+        .name("hello world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .build();
+    assert!(invalid_name.is_err());
+}
+
+#[test]
+fn dependency_to_ruby_yaml() {
+    let dependency = Dependency::new(
+        "rake".to_string(),
+        Requirement::new(vec![(RequirementOperator::Tilde, "13.0".parse().unwrap())]),
+        DependencyType::Development,
+    );
+    let yaml = dependency.to_ruby_yaml();
+
+    assert_eq!(
+        yaml,
+        r#"--- !ruby/object:Gem::Dependency
+name: rake
+requirement: !ruby/object:Gem::Requirement
+  requirements:
+  - - "~>"
+    - !ruby/object:Gem::Version
+      version: '13.0'
+type: :development
+prerelease: false
+version_requirements: !ruby/object:Gem::Requirement
+  requirements:
+  - - "~>"
+    - !ruby/object:Gem::Version
+      version: '13.0'
+"#
+    );
+    assert_eq!(
+        serde_yaml::from_str::<Dependency>(&yaml).unwrap(),
+        dependency
+    );
+}
+
+#[test]
+fn dependency_to_ruby_yaml_quotes_ambiguous_names() {
+    for name in ["1234", "1_000", "0x1f", "true", "yes", "null", "~"] {
+        let dependency = Dependency::new(
+            name.to_string(),
+            Requirement::new(vec![(RequirementOperator::Equal, "1".parse().unwrap())]),
+            DependencyType::Runtime,
+        );
+        let yaml = dependency.to_ruby_yaml();
+
+        assert!(yaml.contains(&format!("name: '{name}'\n")), "{yaml}");
+        assert!(yaml.contains("version: '1'\n"), "{yaml}");
+        assert_eq!(
+            serde_yaml::from_str::<Dependency>(&yaml).unwrap(),
+            dependency,
+            "{name}"
+        );
+    }
+
+    let plain = Dependency::new(
+        "rails-1".to_string(),
+        Requirement::default(),
+        DependencyType::Runtime,
+    );
+    assert!(plain.to_ruby_yaml().contains("name: rails-1\n"));
+}
+
+fn fixture_yaml() -> String {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/hello-world-1.0.0-x86_64-linux.gem");
+    gemspec_rs::gem::Package::new(std::fs::File::open(path).unwrap())
+        .metadata_bytes()
+        .unwrap()
+}
+
+#[test]
+fn string_or_list_fields() {
+    let yaml = fixture_yaml();
+    let sequence = Specification::from_yaml(&yaml).unwrap();
+    assert_eq!(sequence.authors, ["Jane Doe"]);
+
+    let yaml = yaml.replace(
+        "authors:\n- Jane Doe\n",
+        "authors:\n- Jane Doe\n- John Doe\n",
+    );
+    assert_eq!(
+        Specification::from_yaml(&yaml).unwrap().authors,
+        ["Jane Doe", "John Doe"]
+    );
+
+    // Hand-written gemspecs sometimes give a single string instead.
+    let yaml = fixture_yaml()
+        .replace("authors:\n- Jane Doe\n", "authors: Jane Doe\n")
+        .replace("licenses:\n- MIT\n", "licenses: MIT\n")
+        .replace(
+            "executables:\n- hello-world\n",
+            "executables: hello-world\n",
+        );
+    let scalar = Specification::from_yaml(&yaml).unwrap();
+    assert_eq!(scalar.authors, ["Jane Doe"]);
+    assert_eq!(scalar.licenses, ["MIT"]);
+    assert_eq!(scalar.executables, ["hello-world"]);
+    assert_eq!(scalar, sequence);
+}
+
+#[test]
+fn from_yaml_lenient_keeps_unknown_fields() {
+    let yaml = fixture_yaml().replace(
+        "summary: Says hello\n",
+        "summary: Says hello\nfuture_field:\n  nested: true\n",
+    );
+    assert!(Specification::from_yaml(&yaml).is_err());
+
+    let lenient = Specification::from_yaml_lenient(&yaml).unwrap();
+    let strict = Specification::from_yaml(&fixture_yaml()).unwrap();
+    assert_eq!(lenient.extra_fields().len(), 1);
+    assert_eq!(
+        lenient.extra_fields()["future_field"],
+        serde_yaml::from_str::<serde_yaml::Value>("nested: true").unwrap()
+    );
+    assert_eq!(lenient.full_name(), strict.full_name());
+    assert_eq!(lenient.dependencies, strict.dependencies);
+    assert!(strict.extra_fields().is_empty());
+}
+
+#[test]
+fn missing_or_empty_platform_is_ruby() {
+    let yaml = fixture_yaml();
+    assert!(yaml.contains("platform: x86_64-linux\n"));
+
+    for replacement in ["", "platform: ''\n", "platform:\n"] {
+        let spec = Specification::from_yaml(&yaml.replace("platform: x86_64-linux\n", replacement))
+            .unwrap();
+        assert_eq!(spec.platform.as_str(), "ruby", "{replacement:?}");
+        assert_eq!(spec.full_name(), "hello-world-1.0.0");
+    }
+
+    let invalid = yaml.replace("platform: x86_64-linux\n", "platform: x86_64 linux\n");
+    assert!(Specification::from_yaml(&invalid).is_err());
+}
+
+#[test]
+fn platform_serializes_ruby_for_default() {
+    assert_eq!(
+        serde_json::to_string(&gemspec_rs::gem::Platform::new("")).unwrap(),
+        "\"ruby\""
+    );
+    assert_eq!(
+        serde_json::to_string(&gemspec_rs::gem::Platform::default()).unwrap(),
+        "\"ruby\""
+    );
+}
+
+#[test]
+fn dependencies_for_platform() {
+    let dependency = |name: &str| {
+        Dependency::new(
+            name.to_string(),
+            Requirement::new(vec![(
+                RequirementOperator::GreaterThanOrEqual,
+                "0".parse().unwrap(),
+            )]),
+            DependencyType::Runtime,
+        )
+    };
+    let spec = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .add_dependency(dependency("rack"))
+        .add_dependency(dependency("jruby-openssl").with_platform("java".parse().unwrap()))
+        .add_dependency(Dependency::new(
+            "impossible".to_string(),
+            Requirement::new(vec![
+                (RequirementOperator::GreaterThan, "2".parse().unwrap()),
+                (RequirementOperator::LessThan, "1".parse().unwrap()),
+            ]),
+            DependencyType::Runtime,
+        ))
+        .build()
+        .unwrap();
+
+    // `impossible` is left out, as nothing satisfies `> 2, < 1`.
+    let names = |platform: &str| {
+        spec.dependencies_for(&platform.parse().unwrap())
+            .into_iter()
+            .map(Dependency::name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("x86_64-linux"), ["rack"]);
+    assert_eq!(names("java"), ["rack", "jruby-openssl"]);
+}
+
+#[test]
+fn diff_between_releases() {
+    let requirement = |op, version: &str| Requirement::new(vec![(op, version.parse().unwrap())]);
+    let dependency = |name: &str, requirement| {
+        Dependency::new(name.to_string(), requirement, DependencyType::Runtime)
+    };
+    let old = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .license("MIT")
+        .files(["lib/hello_world.rb", "lib/hello_world/legacy.rb"])
+        .add_dependency(dependency(
+            "rack",
+            requirement(RequirementOperator::Tilde, "2.0"),
+        ))
+        .add_dependency(dependency(
+            "json",
+            requirement(RequirementOperator::GreaterThanOrEqual, "0"),
+        ))
+        .build()
+        .unwrap();
+    let mut new = Specification::builder()
+        .name("hello-world")
+        .version("2.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .license("Apache-2.0")
+        .files(["lib/hello_world.rb", "lib/hello_world/cli.rb"])
+        .add_dependency(dependency(
+            "rack",
+            requirement(RequirementOperator::Tilde, "3.0"),
+        ))
+        .add_dependency(dependency(
+            "thor",
+            requirement(RequirementOperator::Tilde, "1.3"),
+        ))
+        .build()
+        .unwrap();
+    new.required_ruby_version = Some(requirement(RequirementOperator::GreaterThanOrEqual, "3.1"));
+
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff.added_dependencies
+            .iter()
+            .map(|d| d.name())
+            .collect::<Vec<_>>(),
+        ["thor"]
+    );
+    assert_eq!(
+        diff.removed_dependencies
+            .iter()
+            .map(|d| d.name())
+            .collect::<Vec<_>>(),
+        ["json"]
+    );
+    assert_eq!(
+        diff.changed_dependencies
+            .keys()
+            .copied()
+            .collect::<Vec<_>>(),
+        ["rack"]
+    );
+    assert_eq!(diff.added_files, ["lib/hello_world/cli.rb"]);
+    assert_eq!(diff.removed_files, ["lib/hello_world/legacy.rb"]);
+    assert!(diff.licenses.is_some());
+    let change = diff.required_ruby_version.as_ref().unwrap();
+    assert_eq!(change.before, None);
+    assert_eq!(change.after, new.required_ruby_version.as_ref());
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(
+        json["changed_dependencies"]["rack"]["after"]["requirements"][0][0],
+        "~>"
+    );
+    assert!(old.diff(&old).is_empty());
+}
+
+#[test]
+fn to_gemfile_lines() {
+    let spec = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe"])
+        .add_dependency(Dependency::new(
+            "rack".to_string(),
+            Requirement::new(vec![
+                (
+                    RequirementOperator::GreaterThanOrEqual,
+                    "2.2".parse().unwrap(),
+                ),
+                (RequirementOperator::LessThan, "4".parse().unwrap()),
+            ]),
+            DependencyType::Runtime,
+        ))
+        .add_dependency(Dependency::new(
+            "json".to_string(),
+            Requirement::new(vec![(
+                RequirementOperator::GreaterThanOrEqual,
+                "0".parse().unwrap(),
+            )]),
+            DependencyType::Runtime,
+        ))
+        .add_dependency(Dependency::new(
+            "rake".to_string(),
+            Requirement::new(vec![(RequirementOperator::Tilde, "13.0".parse().unwrap())]),
+            DependencyType::Development,
+        ))
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        spec.to_gemfile_lines(),
+        [
+            r#"source "https://rubygems.org""#,
+            "",
+            r#"gem "rack", ">= 2.2", "< 4""#,
+            r#"gem "json""#,
+            "",
+            "group :development do",
+            r#"  gem "rake", "~> 13.0""#,
+            "end",
+        ]
+    );
+}
+
+#[test]
+fn dependency_to_lock_string() {
+    let lock = |requirements: Requirement| {
+        Dependency::new("rails".to_string(), requirements, DependencyType::Runtime).to_lock_string()
+    };
+    assert_eq!(
+        lock(Requirement::parse_multiple(&["< 8.0", ">= 7.0"]).unwrap()),
+        "rails (>= 7.0, < 8.0)"
+    );
+    assert_eq!(lock("= 7.1.2".parse().unwrap()), "rails (= 7.1.2)");
+    assert_eq!(lock("~> 7.1".parse().unwrap()), "rails (~> 7.1)");
+    assert_eq!(lock(Requirement::default()), "rails");
+}
+
+#[test]
+fn partial_specification_from_yaml() {
+    let partial = PartialSpecification::from_yaml(
+        "--- !ruby/object:Gem::Specification\nversion: 2.0.0\ndependencies:\n- name: rake\nname: foo\nplatform: [",
+    );
+    assert_eq!(partial.name.as_deref(), Some("foo"));
+    assert_eq!(partial.version.as_deref(), Some("2.0.0"));
+    assert_eq!(partial.platform, None);
+
+    assert_eq!(
+        PartialSpecification::from_yaml(": : :"),
+        PartialSpecification::default()
+    );
+}
+
+#[test]
+fn dependency_type_round_trips() {
+    for r#type in DependencyType::ALL {
+        let displayed = r#type.to_string();
+        assert_eq!(displayed.parse::<DependencyType>().unwrap(), r#type);
+        assert_eq!(serde_yaml::to_string(&r#type).unwrap().trim(), displayed);
+        assert_eq!(
+            serde_yaml::from_str::<DependencyType>(&displayed).unwrap(),
+            r#type
+        );
+    }
+    assert!("runtime".parse::<DependencyType>().is_err());
+    assert!("Runtime".parse::<DependencyType>().is_err());
+}
+
+#[test]
+fn license_expression() {
+    let with_licenses = |licenses: &[&str]| {
+        let mut spec = Specification::default();
+        spec.licenses = licenses.iter().map(|l| l.to_string()).collect();
+        spec
+    };
+
+    assert_eq!(
+        with_licenses(&["MIT", "Apache-2.0"]).license_expression(),
+        Ok("MIT OR Apache-2.0".to_string())
+    );
+    assert_eq!(
+        with_licenses(&["mit", "bsd-2-clause"]).license_expression(),
+        Ok("MIT OR BSD-2-Clause".to_string())
+    );
+    assert_eq!(
+        with_licenses(&["GPL-2.0"]).license_expression(),
+        Ok("GPL-2.0".to_string())
+    );
+    assert_eq!(
+        with_licenses(&["MIT", "The MIT License", "Ruby's"]).license_expression(),
+        Err(vec!["The MIT License".to_string(), "Ruby's".to_string()])
+    );
+}
+
+#[test]
+fn metadata_accessors() {
+    let mut spec = Specification::from_yaml(&fixture_yaml()).unwrap();
+    assert_eq!(spec.homepage_uri(), Some("https://example.com/hello-world"));
+    assert_eq!(
+        spec.source_code_uri(),
+        Some("https://example.com/hello-world/source")
+    );
+    assert_eq!(spec.changelog_uri(), None);
+    assert_eq!(spec.funding_uri(), None);
+    assert!(spec.mfa_required());
+
+    spec.metadata
+        .insert("rubygems_mfa_required".to_string(), "yes".to_string());
+    assert!(!spec.mfa_required());
+    spec.metadata.remove("rubygems_mfa_required");
+    assert!(!spec.mfa_required());
+}
+
+#[test]
+fn homepage_url() {
+    let with_homepage = |homepage: &str| {
+        let mut spec = Specification::default();
+        spec.homepage = homepage.to_string();
+        spec
+    };
+
+    for blank in ["", "  ", "not a url", "mailto:jane@example.com"] {
+        assert_eq!(with_homepage(blank).homepage_url(), None, "{blank:?}");
+        assert!(with_homepage(blank).validate().is_empty(), "{blank:?}");
+    }
+
+    let spec = with_homepage("https://Example.com/hello-world/");
+    assert_eq!(
+        spec.homepage_url().unwrap().as_str(),
+        "https://example.com/hello-world"
+    );
+    assert_eq!(
+        with_homepage("https://example.com")
+            .homepage_url()
+            .unwrap()
+            .as_str(),
+        "https://example.com/"
+    );
+    assert!(spec.validate().is_empty());
+
+    let spec = with_homepage("http://example.com/hello-world");
+    assert_eq!(spec.homepage_url().unwrap().scheme(), "http");
+    assert_eq!(
+        spec.validate(),
+        ["homepage http://example.com/hello-world is not HTTPS"]
+    );
+}
+
+#[test]
+fn serialize_round_trips() {
+    let mut spec = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0.rc1".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe", "John Doe"])
+        .license("MIT")
+        .homepage("https://example.com")
+        .platform("x86_64-linux".parse().unwrap())
+        .files(["lib/hello_world.rb"])
+        .add_dependency(Dependency::new(
+            "rake".to_string(),
+            Requirement::new(vec![
+                (
+                    RequirementOperator::GreaterThanOrEqual,
+                    "13.0".parse().unwrap(),
+                ),
+                (RequirementOperator::LessThan, "14".parse().unwrap()),
+            ]),
+            DependencyType::Development,
+        ))
+        .build()
+        .unwrap();
+    spec.metadata.insert(
+        "source_code_uri".to_string(),
+        "https://example.com/src".to_string(),
+    );
+    spec.description = Some("Says hello, loudly".to_string());
+
+    let yaml = serde_yaml::to_string(&spec).unwrap();
+    assert_eq!(Specification::from_yaml(&yaml).unwrap(), spec, "{yaml}");
+
+    let json = serde_json::to_string(&spec).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Specification>(&json).unwrap(),
+        spec,
+        "{json}"
+    );
+}
+
+#[test]
+fn platform_and_prerelease_predicates() {
+    let spec = |version: &str, platform: &str| {
+        Specification::builder()
+            .name("hello-world")
+            .version(version.parse().unwrap())
+            .summary("Says hello")
+            .authors(["Jane Doe"])
+            .platform(platform.parse().unwrap())
+            .build()
+            .unwrap()
+    };
+
+    let ruby = spec("1.0.0", "ruby");
+    assert!(!ruby.is_platform_gem());
+    assert!(!ruby.is_prerelease());
+    assert_eq!(ruby.gem_file_name(), "hello-world-1.0.0.gem");
+
+    let native = spec("1.0.0.rc1", "x86_64-linux");
+    assert!(native.is_platform_gem());
+    assert!(native.is_prerelease());
+    assert_eq!(
+        native.gem_file_name(),
+        "hello-world-1.0.0.rc1-x86_64-linux.gem"
+    );
+}
+
+#[test]
+fn to_api_json() {
+    let mut spec = Specification::builder()
+        .name("hello-world")
+        .version("1.0.0".parse().unwrap())
+        .summary("Says hello")
+        .authors(["Jane Doe", "John Doe"])
+        .license("MIT")
+        .homepage("https://example.com")
+        .add_dependency(Dependency::new(
+            "rack".to_string(),
+            Requirement::new(vec![
+                (
+                    RequirementOperator::GreaterThanOrEqual,
+                    "2.0".parse().unwrap(),
+                ),
+                (RequirementOperator::LessThan, "4".parse().unwrap()),
+            ]),
+            DependencyType::Runtime,
+        ))
+        .add_dependency(Dependency::new(
+            "rake".to_string(),
+            Requirement::new(vec![(RequirementOperator::Tilde, "13.0".parse().unwrap())]),
+            DependencyType::Development,
+        ))
+        .build()
+        .unwrap();
+    spec.metadata.insert(
+        "source_code_uri".to_string(),
+        "https://example.com/src".to_string(),
+    );
+
+    assert_eq!(
+        spec.to_api_json(),
+        serde_json::json!({
+            "name": "hello-world",
+            "version": "1.0.0",
+            "platform": "ruby",
+            "authors": "Jane Doe, John Doe",
+            "info": "Says hello",
+            "licenses": ["MIT"],
+            "metadata": {"source_code_uri": "https://example.com/src"},
+            "homepage_uri": "https://example.com",
+            "wiki_uri": null,
+            "documentation_uri": null,
+            "mailing_list_uri": null,
+            "source_code_uri": "https://example.com/src",
+            "bug_tracker_uri": null,
+            "changelog_uri": null,
+            "funding_uri": null,
+            "dependencies": {
+                "development": [{"name": "rake", "requirements": "~> 13.0"}],
+                "runtime": [{"name": "rack", "requirements": ">= 2.0, < 4"}],
+            },
+        })
+    );
+
+    spec.description = Some("Says hello, loudly".to_string());
+    assert_eq!(spec.to_api_json()["info"], "Says hello, loudly");
+}
+
+#[test]
+fn effective_required_ruby_version() {
+    let mut spec = Specification::default();
+    assert_eq!(spec.required_ruby_version, None);
+    assert!(spec.effective_required_ruby_version().is_none());
+    assert_eq!(spec.effective_required_ruby_version().to_string(), ">= 0");
+    assert!(spec.supports_ruby(&"1.8.7".parse().unwrap()).unwrap());
+
+    spec.required_ruby_version = Some(Requirement::new(vec![]));
+    assert_eq!(
+        spec.effective_required_ruby_version(),
+        Requirement::default()
+    );
+
+    let requirement = Requirement::at_least("3.1".parse().unwrap());
+    spec.required_ruby_version = Some(requirement.clone());
+    assert_eq!(spec.effective_required_ruby_version(), requirement);
+    assert!(!spec.supports_ruby(&"3.0.7".parse().unwrap()).unwrap());
+}
@@ -0,0 +1,305 @@
+use std::cmp::Ordering;
+
+use gemspec_rs::gem::{Version, VersionSegment};
+use proptest::prelude::*;
+
+fn canonicalize(version: &str) -> String {
+    version
+        .parse::<Version>()
+        .unwrap()
+        .canonicalize()
+        .as_str()
+        .to_string()
+}
+
+#[test]
+fn canonicalize_drops_trailing_zeros() {
+    assert_eq!(canonicalize("1.2.0"), "1.2");
+    assert_eq!(canonicalize("1.2.0.0"), "1.2");
+    assert_eq!(canonicalize("1.0"), "1");
+    assert_eq!(canonicalize("0.0.0"), "0");
+    assert_eq!(canonicalize("1.10"), "1.10");
+}
+
+#[test]
+fn canonicalize_keeps_prereleases() {
+    assert_eq!(canonicalize("1.0.0.rc1"), "1.0.0.rc1");
+    assert_eq!(canonicalize("1.0.0.pre.0"), "1.0.0.pre.0");
+}
+
+#[test]
+fn release_and_prerelease_segments() {
+    let version: Version = "1.0.0.rc.1".parse().unwrap();
+
+    assert_eq!(
+        version.release_segments(),
+        [
+            VersionSegment::Number(1),
+            VersionSegment::Number(0),
+            VersionSegment::Number(0)
+        ]
+    );
+    assert_eq!(
+        version.prerelease_segments(),
+        [
+            VersionSegment::String("rc".to_string()),
+            VersionSegment::Number(1)
+        ]
+    );
+    assert_eq!(version.release().as_str(), "1.0.0");
+
+    let release: Version = "2.1".parse().unwrap();
+    assert_eq!(release.release_segments().len(), 2);
+    assert!(release.prerelease_segments().is_empty());
+}
+
+#[test]
+fn parse_errors_are_typed() {
+    assert!(matches!(
+        "1..0".parse::<Version>(),
+        Err(gemspec_rs::gem::Error::VersionParse(_))
+    ));
+}
+
+#[test]
+fn rejects_invalid_versions() {
+    for version in [
+        "",
+        ".1",
+        "1.",
+        "1..0",
+        "1:2.3",
+        "a.1",
+        "1.0 beta",
+        "1.0_1",
+        "1.0-",
+        "1.0-rc..1",
+        "1.0-rc+1",
+    ] {
+        assert!(
+            matches!(
+                version.parse::<Version>(),
+                Err(gemspec_rs::gem::Error::VersionParse(_))
+            ),
+            "{version:?}"
+        );
+    }
+}
+
+#[test]
+fn dashes_are_prereleases() {
+    let version: Version = "1.0-rc1".parse().unwrap();
+    assert_eq!(version.as_str(), "1.0.pre.rc1");
+    assert_eq!(version, "1.0.pre.rc1".parse().unwrap());
+    assert!(version < "1.0".parse().unwrap());
+
+    assert_eq!(
+        " 2.0-beta-2 ".parse::<Version>().unwrap().as_str(),
+        "2.0.pre.beta.pre.2"
+    );
+}
+
+#[test]
+fn parse_all_keeps_going() {
+    let (parsed, failed) = Version::parse_all(["1.0", "1..0", "2.0.rc1", "1:2.3"]);
+
+    assert_eq!(
+        parsed.iter().map(Version::as_str).collect::<Vec<_>>(),
+        ["1.0", "2.0.rc1"]
+    );
+    assert_eq!(
+        failed.iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>(),
+        ["1..0", "1:2.3"]
+    );
+    assert!(
+        failed
+            .iter()
+            .all(|(_, e)| matches!(e, gemspec_rs::gem::Error::VersionParse(_)))
+    );
+}
+
+#[test]
+fn minor_series_and_truncate() {
+    let version = |v: &str| v.parse::<Version>().unwrap();
+
+    assert_eq!(version("1.2.3").minor_series(), (Some(1), Some(2)));
+    assert_eq!(version("1").minor_series(), (Some(1), None));
+    assert_eq!(version("1.a").minor_series(), (Some(1), None));
+
+    assert_eq!(version("1.2.3.rc1").truncate(2).as_str(), "1.2");
+    assert_eq!(version("1.2.3").truncate(3).as_str(), "1.2.3");
+    assert_eq!(version("1.2").truncate(5).as_str(), "1.2");
+    assert_eq!(version("1.2").truncate(5), version("1.2"));
+    assert_eq!(version(version("1.2.3").truncate(1).as_str()), version("1"));
+}
+
+#[test]
+#[should_panic(expected = "no segments")]
+fn truncate_to_nothing_panics() {
+    "1.2.3".parse::<Version>().unwrap().truncate(0);
+}
+
+#[test]
+fn parse_lenient_strips_v_prefix() {
+    let strict: Version = "1.2.3".parse().unwrap();
+    for tag in ["v1.2.3", "V1.2.3", " v1.2.3\n", "1.2.3"] {
+        let lenient = Version::parse_lenient(tag).unwrap();
+        assert_eq!(lenient, strict, "{tag:?}");
+        assert_eq!(lenient.as_str(), "1.2.3", "{tag:?}");
+    }
+    assert!("v1.2.3".parse::<Version>().is_err());
+    assert!(Version::parse_lenient("vv1.2.3").is_err());
+    assert!(Version::parse_lenient("v").is_err());
+}
+
+#[test]
+fn serializes_plain_or_as_ruby_object() {
+    #[derive(serde::Serialize)]
+    struct RubyObject {
+        #[serde(serialize_with = "Version::serialize_ruby_object")]
+        version: Version,
+    }
+
+    let version: Version = "1.2.3".parse().unwrap();
+    assert_eq!(version.to_plain_string(), "1.2.3");
+
+    let json = serde_json::to_string(&version).unwrap();
+    assert_eq!(json, r#""1.2.3""#);
+    assert_eq!(serde_json::from_str::<Version>(&json).unwrap(), version);
+
+    let yaml = serde_yaml::to_string(&RubyObject {
+        version: version.clone(),
+    })
+    .unwrap();
+    assert_eq!(
+        yaml,
+        "version: !ruby/object:Gem::Version\n  version: 1.2.3\n"
+    );
+    let node = yaml.strip_prefix("version: ").unwrap();
+    assert_eq!(serde_yaml::from_str::<Version>(node).unwrap(), version);
+
+    let json = serde_json::to_string(&RubyObject {
+        version: version.clone(),
+    })
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        serde_json::from_value::<Version>(value["version"].clone()).unwrap(),
+        version
+    );
+}
+
+#[test]
+fn display_matches_as_str() {
+    for version in ["1.0", "1.0.0.rc1", "2.a.3", "0"] {
+        let parsed: Version = version.parse().unwrap();
+        assert_eq!(parsed.to_string(), version);
+        assert_eq!(format!("{parsed}"), parsed.as_str());
+    }
+    // Serialization is unaffected.
+    let version: Version = "1.2.3".parse().unwrap();
+    assert_eq!(serde_json::to_string(&version).unwrap(), r#""1.2.3""#);
+}
+
+#[test]
+fn bump() {
+    let bump = |version: &str| version.parse::<Version>().unwrap().bump().to_string();
+    assert_eq!(bump("5.3.1"), "5.4");
+    assert_eq!(bump("5.3.0"), "5.4");
+    assert_eq!(bump("5.0"), "6");
+    assert_eq!(bump("1.2.3.rc1"), "1.3");
+    assert_eq!(bump("5"), "6");
+    // RubyGems splits `3a` into `3` and `a`, same as `3.a`.
+    assert_eq!(bump("1.2.3a"), "1.3");
+    assert_eq!(bump("1.2.3.a"), "1.3");
+}
+
+#[test]
+fn total_cmp_breaks_ties_by_string() {
+    let v = |s: &str| s.parse::<Version>().unwrap();
+    assert_eq!(v("1.0").cmp(&v("1.0.0")), Ordering::Equal);
+    assert_eq!(v("1.0").total_cmp(&v("1.0.0")), Ordering::Less);
+    assert_eq!(v("1.0.0").total_cmp(&v("1.0")), Ordering::Greater);
+    assert_eq!(v("1.0.a").total_cmp(&v("1.a")), Ordering::Less);
+    assert_eq!(v("1.10").total_cmp(&v("1.9")), Ordering::Greater);
+    assert_eq!(v("1.0").total_cmp(&v("1.0")), Ordering::Equal);
+}
+
+#[test]
+fn eq_exact_compares_strings() {
+    let v = |s: &str| s.parse::<Version>().unwrap();
+    assert_eq!(v("1.0"), v("1.0.0"));
+    assert!(!v("1.0").eq_exact(&v("1.0.0")));
+    assert!(v("1.0").eq_exact(&v("1.0")));
+    assert!(!v("1.0.a").eq_exact(&v("1.0.A")));
+}
+
+/// Valid versions, mixing numeric and string segments of different
+/// lengths, prereleases after `-`, and numbers too big for a `u64`.
+fn version() -> impl Strategy<Value = Version> {
+    "[0-9]{1,2}(\\.([0-9]{1,2}|[a-c]{1,2}|0|[0-9]{21}))*(-[a-c0-9]{1,2}(\\.[a-c0-9]{1,2})*)?"
+        .prop_map(|s| s.parse().unwrap())
+}
+
+fn check(cmp: fn(&Version, &Version) -> Ordering, a: &Version, b: &Version, c: &Version) {
+    assert_eq!(cmp(a, a), Ordering::Equal);
+    assert_eq!(cmp(a, b), cmp(b, a).reverse());
+    if cmp(a, b).is_le() && cmp(b, c).is_le() {
+        assert!(cmp(a, c).is_le(), "{a:?} <= {b:?} <= {c:?}");
+    }
+    if cmp(a, b).is_eq() && cmp(b, c).is_eq() {
+        assert!(cmp(a, c).is_eq(), "{a:?} == {b:?} == {c:?}");
+    }
+}
+
+proptest! {
+    #[test]
+    fn cmp_is_a_total_order(a in version(), b in version(), c in version()) {
+        check(Version::cmp, &a, &b, &c);
+        check(Version::total_cmp, &a, &b, &c);
+        if a.total_cmp(&b).is_eq() {
+            prop_assert_eq!(a.as_str(), b.as_str());
+        }
+        prop_assert_eq!(a.eq_exact(&b), a.total_cmp(&b).is_eq());
+    }
+}
+
+#[test]
+fn prerelease_navigation() {
+    let v = |s: &str| s.parse::<Version>().unwrap();
+    let next = |s: &str, tag: &str| v(s).next_prerelease(tag).as_str().to_string();
+
+    let pre1 = v("1.2.0").next_prerelease("pre");
+    assert_eq!(pre1.as_str(), "1.2.0.pre.1");
+    let pre2 = pre1.next_prerelease("pre");
+    assert_eq!(pre2.as_str(), "1.2.0.pre.2");
+    assert!(v("1.2.0.pre.1") < pre2 && pre2 < v("1.2.0"));
+
+    assert_eq!(next("1.2.0.pre.9", "pre"), "1.2.0.pre.10");
+    assert_eq!(next("1.2.0.rc.3", "pre"), "1.2.0.pre.1");
+    assert_eq!(next("1.2.0.pre", "pre"), "1.2.0.pre.1");
+
+    assert_eq!(v("1.2.0").with_prerelease("beta").as_str(), "1.2.0.beta");
+    assert_eq!(
+        v("1.2.0.rc.2").with_prerelease("beta").as_str(),
+        "1.2.0.beta"
+    );
+    assert_eq!(
+        v("1.2.0.rc.2").with_prerelease("pre.1"),
+        v("1.2.0").next_prerelease("pre")
+    );
+}
+
+#[test]
+fn prerelease_tags_must_parse_back() {
+    let v = |s: &str| s.parse::<Version>().unwrap();
+
+    for tag in ["", "pre..1", "1", "pre-1"] {
+        let with = std::panic::catch_unwind(|| v("1.2.0").with_prerelease(tag));
+        assert!(with.is_err(), "{tag:?}");
+    }
+    for tag in ["", "pre.1"] {
+        let next = std::panic::catch_unwind(|| v("1.2.0").next_prerelease(tag));
+        assert!(next.is_err(), "{tag:?}");
+    }
+}